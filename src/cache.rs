@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::client::Deck;
+
+/// Last known deck list plus every deck whose cards were fetched, so that
+/// studying keeps working while Toshokan is unreachable.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DeckCache {
+    #[serde(default)]
+    pub decks: Vec<Deck>,
+    #[serde(default)]
+    pub full: HashMap<String, Deck>,
+}
+
+impl DeckCache {
+    pub fn is_available_offline(&self, id: &str) -> bool {
+        self.full.contains_key(id)
+    }
+
+    /// Replaces the cached list. Full decks that no longer appear in it were
+    /// deleted elsewhere and are dropped.
+    pub fn store_list(&mut self, decks: &[Deck]) {
+        self.decks = decks
+            .iter()
+            .map(|deck| Deck {
+                cards: Vec::new(),
+                ..deck.clone()
+            })
+            .collect();
+        self.full
+            .retain(|id, _| decks.iter().any(|deck| &deck.id == id));
+    }
+
+    pub fn store_deck(&mut self, deck: &Deck) {
+        if !self.decks.iter().any(|d| d.id == deck.id) {
+            self.decks.push(Deck {
+                cards: Vec::new(),
+                ..deck.clone()
+            });
+        }
+        self.full.insert(deck.id.clone(), deck.clone());
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.decks.retain(|deck| deck.id != id);
+        self.full.remove(id);
+    }
+
+    /// The cached list with cards filled in wherever they are available,
+    /// paired with whether each deck is complete.
+    pub fn decks(&self) -> (Vec<Deck>, Vec<bool>) {
+        self.decks
+            .iter()
+            .map(|deck| match self.full.get(&deck.id) {
+                Some(full) => (full.clone(), true),
+                None => (deck.clone(), false),
+            })
+            .unzip()
+    }
+}
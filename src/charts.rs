@@ -0,0 +1,235 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use iced::widget::canvas::{self, Cursor, Frame, Geometry, Path, Stroke, Text};
+use iced::{alignment, Color, Point, Rectangle, Size, Theme};
+
+const LABEL_SIZE: f32 = 12.0;
+/// Room kept above and below the plot for value and axis labels.
+const LABEL_SPACE: f32 = 16.0;
+const AXIS_COLOR: Color = Color {
+    r: 0.55,
+    g: 0.55,
+    b: 0.55,
+    a: 1.0,
+};
+
+fn label(content: String, position: Point, horizontal: alignment::Horizontal) -> Text {
+    Text {
+        content,
+        position,
+        color: AXIS_COLOR,
+        size: LABEL_SIZE,
+        horizontal_alignment: horizontal,
+        vertical_alignment: alignment::Vertical::Center,
+        ..Text::default()
+    }
+}
+
+fn baseline(frame: &mut Frame, bounds: Rectangle) {
+    let y = bounds.height - LABEL_SPACE;
+    frame.stroke(
+        &Path::line(Point::new(0.0, y), Point::new(bounds.width, y)),
+        Stroke::default().with_color(AXIS_COLOR).with_width(1.0),
+    );
+}
+
+/// One vertical bar per labelled value.
+pub struct BarChart {
+    pub bars: Vec<(String, f32)>,
+    pub color: Color,
+    /// Only every n-th bar gets its label drawn, for charts with many bars.
+    pub label_every: usize,
+}
+
+impl<Message> canvas::Program<Message> for BarChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let plot_height = bounds.height - LABEL_SPACE * 2.0;
+        let max = self
+            .bars
+            .iter()
+            .map(|(_, value)| *value)
+            .fold(0.0, f32::max)
+            .max(1.0);
+        let slot = bounds.width / self.bars.len().max(1) as f32;
+
+        for (index, (name, value)) in self.bars.iter().enumerate() {
+            let height = value / max * plot_height;
+            let x = index as f32 * slot;
+            let top = LABEL_SPACE + plot_height - height;
+            frame.fill_rectangle(
+                Point::new(x + slot * 0.15, top),
+                Size::new(slot * 0.7, height),
+                self.color,
+            );
+
+            let center = x + slot / 2.0;
+            if *value > 0.0 && slot >= LABEL_SIZE * 1.5 {
+                frame.fill_text(label(
+                    format!("{}", value),
+                    Point::new(center, top - LABEL_SPACE / 2.0),
+                    alignment::Horizontal::Center,
+                ));
+            }
+            if index % self.label_every.max(1) == 0 {
+                frame.fill_text(label(
+                    name.clone(),
+                    Point::new(center, bounds.height - LABEL_SPACE / 2.0),
+                    alignment::Horizontal::Center,
+                ));
+            }
+        }
+        baseline(&mut frame, bounds);
+
+        vec![frame.into_geometry()]
+    }
+}
+
+/// A line through evenly spaced values between zero and `max`.
+pub struct LineChart {
+    pub values: Vec<f32>,
+    pub max: f32,
+    pub color: Color,
+    /// Labels under the first and the last value.
+    pub first_label: String,
+    pub last_label: String,
+}
+
+impl<Message> canvas::Program<Message> for LineChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let plot_height = bounds.height - LABEL_SPACE * 2.0;
+        let step = bounds.width / self.values.len().saturating_sub(1).max(1) as f32;
+        let points: Vec<Point> = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let ratio = (value / self.max.max(f32::EPSILON)).clamp(0.0, 1.0);
+                Point::new(
+                    index as f32 * step,
+                    LABEL_SPACE + plot_height * (1.0 - ratio),
+                )
+            })
+            .collect();
+
+        if points.len() > 1 {
+            let line = Path::new(|builder| {
+                builder.move_to(points[0]);
+                for point in points.iter().skip(1) {
+                    builder.line_to(*point);
+                }
+            });
+            frame.stroke(
+                &line,
+                Stroke::default().with_color(self.color).with_width(2.0),
+            );
+        }
+        for point in points.iter() {
+            frame.fill(&Path::circle(*point, 3.0), self.color);
+        }
+
+        frame.fill_text(label(
+            format!("{}", self.max),
+            Point::new(0.0, LABEL_SPACE / 2.0),
+            alignment::Horizontal::Left,
+        ));
+        frame.fill_text(label(
+            self.first_label.clone(),
+            Point::new(0.0, bounds.height - LABEL_SPACE / 2.0),
+            alignment::Horizontal::Left,
+        ));
+        frame.fill_text(label(
+            self.last_label.clone(),
+            Point::new(bounds.width, bounds.height - LABEL_SPACE / 2.0),
+            alignment::Horizontal::Right,
+        ));
+        baseline(&mut frame, bounds);
+
+        vec![frame.into_geometry()]
+    }
+}
+
+const HEATMAP_CELL: f32 = 10.0;
+const HEATMAP_GAP: f32 = 2.0;
+
+/// Reviews per day laid out as one column per week, Monday on top.
+pub struct Heatmap {
+    /// First day drawn, a Monday.
+    pub start: NaiveDate,
+    /// Reviews on each day from `start` on.
+    pub counts: Vec<usize>,
+    /// Count drawn in full colour; fewer reviews are drawn lighter.
+    pub goal: usize,
+    pub color: Color,
+}
+
+impl Heatmap {
+    pub fn width(weeks: usize) -> f32 {
+        weeks as f32 * (HEATMAP_CELL + HEATMAP_GAP)
+    }
+
+    pub fn height() -> f32 {
+        LABEL_SPACE + 7.0 * (HEATMAP_CELL + HEATMAP_GAP)
+    }
+}
+
+impl<Message> canvas::Program<Message> for Heatmap {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let step = HEATMAP_CELL + HEATMAP_GAP;
+
+        for (offset, count) in self.counts.iter().enumerate() {
+            let day = self.start + Duration::days(offset as i64);
+            let week = offset / 7;
+            let weekday = day.weekday().num_days_from_monday();
+            let x = week as f32 * step;
+
+            if weekday == 0 && day.day() <= 7 {
+                frame.fill_text(label(
+                    day.format("%b").to_string(),
+                    Point::new(x, LABEL_SPACE / 2.0),
+                    alignment::Horizontal::Left,
+                ));
+            }
+
+            let intensity = match count {
+                0 => 0.1,
+                count => 0.3 + 0.7 * (*count as f32 / self.goal.max(1) as f32).min(1.0),
+            };
+            frame.fill_rectangle(
+                Point::new(x, LABEL_SPACE + weekday as f32 * step),
+                Size::new(HEATMAP_CELL, HEATMAP_CELL),
+                Color {
+                    a: intensity,
+                    ..self.color
+                },
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
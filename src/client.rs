@@ -1,45 +1,267 @@
-use graphql_client::{reqwest::post_graphql, GraphQLQuery};
+use chrono::{DateTime, Utc};
+use graphql_client::GraphQLQuery;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{self, Display};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 
 use self::{
     get_popular_decks::GetPopularDecksPopularDecks,
+    get_public_decks::GetPublicDecksPublicDecks,
     new_deck::{
         CreateAnswerInput, CreateCardInput, CreateDeckInput, NewDeckCreateDeck,
         NewDeckCreateDeckDeck,
     },
     obtain_deck::ObtainDeckDeck,
+    obtain_profile::ObtainProfileProfile,
+    obtain_progress::{
+        ObtainProgressProgress, ObtainProgressProgressCardStates, ObtainProgressProgressReviews,
+    },
+    record_progress::{CardStateInput, RecordProgressInput, ReviewInput},
+    update_profile::{UpdateProfileInput, UpdateProfileUpdateProfileProfile},
 };
+use crate::history::Review;
+use crate::scheduler::{CardState, Rating};
 
 const GRAPHQL_URL: &str = "http://localhost:8080/query";
 const SIGNUP_URL: &str = "http://localhost:8080/signup";
 const LOGIN_URL: &str = "http://localhost:8080/login";
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone)]
 pub enum Error {
-    PayloadError,
-    APIError,
+    /// The response could not be decoded or lacked the expected fields.
+    PayloadError(String),
+    /// The server answered with GraphQL errors instead of data.
+    GraphQLError {
+        status: reqwest::StatusCode,
+        errors: Vec<GraphQLError>,
+    },
+    /// Any other non-successful HTTP status, with the response body.
+    APIError {
+        status: reqwest::StatusCode,
+        message: String,
+    },
     AuthError,
     NotFound,
-    NetworkError,
+    /// The request never got a response; holds the transport cause.
+    NetworkError(Arc<reqwest::Error>),
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphQLError {
+    pub message: String,
+    pub path: Vec<String>,
+    pub extensions: HashMap<String, serde_json::Value>,
+}
+
+impl GraphQLError {
+    fn code(&self) -> Option<&str> {
+        self.extensions.get("code").and_then(|code| code.as_str())
+    }
+}
+
+impl From<graphql_client::Error> for GraphQLError {
+    fn from(error: graphql_client::Error) -> Self {
+        GraphQLError {
+            message: error.message,
+            path: error
+                .path
+                .unwrap_or_default()
+                .iter()
+                .map(|fragment| fragment.to_string())
+                .collect(),
+            extensions: error.extensions.unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for GraphQLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.path.is_empty() {
+            write!(f, " (at {})", self.path.join("."))?;
+        }
+        if let Some(code) = self.code() {
+            write!(f, " [{}]", code)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    fn from_response(status: reqwest::StatusCode, body: String) -> Self {
+        if let Ok(response) =
+            serde_json::from_str::<graphql_client::Response<serde_json::Value>>(&body)
+        {
+            if let Some(errors) = response.errors {
+                if !errors.is_empty() {
+                    return Error::from_graphql_errors(status, errors);
+                }
+            }
+        }
+
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED => Error::AuthError,
+            reqwest::StatusCode::NOT_FOUND => Error::NotFound,
+            _ => Error::APIError {
+                status,
+                message: body.trim().to_owned(),
+            },
+        }
+    }
+
+    fn from_graphql_errors(
+        status: reqwest::StatusCode,
+        errors: Vec<graphql_client::Error>,
+    ) -> Self {
+        let errors: Vec<GraphQLError> = errors.into_iter().map(GraphQLError::from).collect();
+
+        let unauthenticated = errors
+            .iter()
+            .any(|error| error.code() == Some("UNAUTHENTICATED"));
+        if status == reqwest::StatusCode::UNAUTHORIZED || unauthenticated {
+            return Error::AuthError;
+        }
+
+        Error::GraphQLError { status, errors }
+    }
+
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Error::GraphQLError { status, .. } | Error::APIError { status, .. } => Some(*status),
+            Error::AuthError => Some(reqwest::StatusCode::UNAUTHORIZED),
+            Error::NotFound => Some(reqwest::StatusCode::NOT_FOUND),
+            Error::NetworkError(err) => err.status(),
+            Error::PayloadError(_) => None,
+        }
+    }
 }
 
 impl From<reqwest::Error> for Error {
     fn from(error: reqwest::Error) -> Self {
-        dbg!(error);
+        if error.is_decode() {
+            return Error::PayloadError(error.to_string());
+        }
 
-        Error::NetworkError
+        Error::NetworkError(Arc::new(error))
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::PayloadError => write!(f, "Malformed json"),
-            Error::APIError => write!(f, "Server error"),
+            Error::PayloadError(reason) => write!(f, "Malformed response: {}", reason),
+            Error::GraphQLError { errors, .. } => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "Server rejected the request: {}", messages.join("; "))
+            }
+            Error::APIError { status, message } => {
+                if message.is_empty() {
+                    write!(f, "Server error ({})", status)
+                } else {
+                    write!(f, "Server error ({}): {}", status, message)
+                }
+            }
             Error::AuthError => write!(f, "Wrong login"),
             Error::NotFound => write!(f, "Not found"),
-            Error::NetworkError => write!(f, "Network error"),
+            Error::NetworkError(err) => {
+                write!(f, "Network error: {}", err)?;
+                let mut source = std::error::Error::source(err.as_ref());
+                while let Some(cause) = source {
+                    write!(f, ": {}", cause)?;
+                    source = cause.source();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::NetworkError(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// How often, and how patiently, a failed request is sent again.
+///
+/// Only failures that may go away on their own are retried: for queries any
+/// network error or 5xx/429 answer, for mutations only connection failures,
+/// where the server is known not to have received the request.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Exponential backoff with equal jitter: half of the capped delay is
+    /// fixed, the other half random, so clients don't retry in lockstep.
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = exponential / 2;
+
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+
+    fn should_retry(&self, error: &Error, idempotent: bool) -> bool {
+        match error {
+            Error::NetworkError(err) => idempotent || err.is_connect(),
+            Error::APIError { status, .. } => {
+                idempotent
+                    && (status.is_server_error()
+                        || *status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+            }
+            _ => false,
+        }
+    }
+
+    async fn run<T, F, Fut>(&self, idempotent: bool, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(err)
+                    if attempt + 1 < self.max_attempts && self.should_retry(&err, idempotent) =>
+                {
+                    let delay = self.delay(attempt);
+                    eprintln!("{} (retrying in {:?})", err, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
         }
     }
 }
@@ -49,10 +271,28 @@ pub struct Token {
     pub token: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Deck {
     #[serde(default = "empty_cards")]
     pub cards: Vec<Card>,
+    #[serde(default)]
+    pub owner_id: String,
+    /// Folder path from the top level down, empty for decks outside folders.
+    #[serde(default)]
+    pub folder: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Number of cards, known even when `cards` has not been fetched.
+    #[serde(default)]
+    pub card_count: usize,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Public decks are listed for every Toshokan user.
+    #[serde(default)]
+    pub is_public: bool,
+    /// Name of the owner, only filled in for other users' public decks.
+    #[serde(default)]
+    pub author: String,
     pub description: String,
     pub title: String,
     pub id: String,
@@ -62,14 +302,18 @@ fn empty_cards() -> Vec<Card> {
     Vec::new()
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Card {
+    #[serde(default)]
+    pub id: String,
     pub title: String,
     pub answers: Vec<Answer>,
     pub explanation: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Answer {
     pub text: String,
     #[serde(default = "false_answer")]
@@ -94,181 +338,250 @@ pub struct Login {
     pub password: String,
 }
 
-pub async fn sign_up(signup: Signup) -> Result<Token, Error> {
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Profile {
+    pub id: String,
+    pub username: String,
+    pub display_name: String,
+    pub bio: String,
+}
+
+pub async fn sign_up(signup: Signup, retry: RetryPolicy) -> Result<Token, Error> {
     let client = reqwest::Client::new();
-    let response = client.post(SIGNUP_URL).json(&signup).send().await;
-    println!("{:#?}", response);
-
-    match response {
-        Ok(res) => match res.status() {
-            reqwest::StatusCode::OK => {
-                let token = res.json::<Token>().await;
-                match token {
-                    Ok(t) => Ok(t),
-                    Err(_) => Err(Error::PayloadError),
-                }
+    retry
+        .run(false, || async {
+            let response = client.post(SIGNUP_URL).json(&signup).send().await;
+
+            match response {
+                Ok(res) => match res.status() {
+                    reqwest::StatusCode::OK => {
+                        let token = res.json::<Token>().await;
+                        match token {
+                            Ok(t) => Ok(t),
+                            Err(err) => Err(Error::PayloadError(err.to_string())),
+                        }
+                    }
+                    status => Err(Error::from_response(
+                        status,
+                        res.text().await.unwrap_or_default(),
+                    )),
+                },
+                Err(err) => Err(Error::from(err)),
             }
-            _ => Err(res.status().into()),
-        },
-        Err(err) => Err(Error::from(err)),
-    }
+        })
+        .await
 }
 
-pub async fn log_in(login: Login) -> Result<Token, Error> {
+pub async fn log_in(login: Login, retry: RetryPolicy) -> Result<Token, Error> {
     let client = reqwest::Client::new();
-    let response = client.post(LOGIN_URL).json(&login).send().await;
-    println!("{:#?}", response);
-
-    match response {
-        Ok(res) => match res.status() {
-            reqwest::StatusCode::OK => {
-                let token = res.json::<Token>().await;
-                match token {
-                    Ok(t) => Ok(t),
-                    Err(_) => Err(Error::PayloadError),
-                }
+    retry
+        .run(true, || async {
+            let response = client.post(LOGIN_URL).json(&login).send().await;
+
+            match response {
+                Ok(res) => match res.status() {
+                    reqwest::StatusCode::OK => {
+                        let token = res.json::<Token>().await;
+                        match token {
+                            Ok(t) => Ok(t),
+                            Err(err) => Err(Error::PayloadError(err.to_string())),
+                        }
+                    }
+                    status => Err(Error::from_response(
+                        status,
+                        res.text().await.unwrap_or_default(),
+                    )),
+                },
+                Err(err) => Err(Error::from(err)),
             }
-            _ => Err(res.status().into()),
-        },
-        Err(err) => Err(Error::from(err)),
-    }
+        })
+        .await
 }
 
-impl From<reqwest::StatusCode> for Error {
-    fn from(code: reqwest::StatusCode) -> Self {
-        match code {
-            reqwest::StatusCode::UNAUTHORIZED => Error::AuthError,
-            reqwest::StatusCode::NOT_FOUND => Error::NotFound,
-            _ => Error::APIError,
-        }
-    }
+fn authorized_client(token: &str) -> Result<reqwest::Client, Error> {
+    let header_value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+        .map_err(|_| Error::AuthError)?;
+
+    let client = reqwest::Client::builder()
+        .default_headers(std::iter::once((reqwest::header::AUTHORIZATION, header_value)).collect())
+        .build()?;
+
+    Ok(client)
 }
 
-pub async fn get_decks(token: String) -> Result<Vec<Deck>, Error> {
-    let client: reqwest::Client;
-    if let Ok(c) = reqwest::Client::builder()
-        .default_headers(
-            std::iter::once((
-                reqwest::header::AUTHORIZATION,
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
-            ))
-            .collect(),
-        )
-        .build()
-    {
-        client = c;
-    } else {
-        return Err(Error::NetworkError);
-    }
+/// Sends a GraphQL operation and returns its data, turning HTTP statuses,
+/// GraphQL `errors` and transport failures into the matching `Error`.
+/// Idempotent operations are retried according to `retry`; others only
+/// when they could not reach the server.
+async fn post_query<Q: GraphQLQuery>(
+    token: &str,
+    variables: Q::Variables,
+    idempotent: bool,
+    retry: RetryPolicy,
+) -> Result<Q::ResponseData, Error> {
+    let client = authorized_client(token)?;
+    let body = Q::build_query(variables);
 
-    let response = post_graphql::<GetPopularDecks, _>(
-        &client,
-        GRAPHQL_URL,
-        get_popular_decks::Variables { first: Some(50) },
-    )
-    .await;
+    retry
+        .run(idempotent, || send_query::<Q>(&client, &body))
+        .await
+}
 
-    match response {
-        Ok(response) => {
-            if let Some(response_body) = response.data {
-                if let Some(decks_ql) = response_body.popular_decks {
-                    return Ok(decks_ql.into());
-                }
+async fn send_query<Q: GraphQLQuery>(
+    client: &reqwest::Client,
+    body: &graphql_client::QueryBody<Q::Variables>,
+) -> Result<Q::ResponseData, Error> {
+    let res = client.post(GRAPHQL_URL).json(body).send().await?;
 
-                return Err(Error::PayloadError);
-            }
+    let status = res.status();
+    if !status.is_success() {
+        return Err(Error::from_response(
+            status,
+            res.text().await.unwrap_or_default(),
+        ));
+    }
 
-            return Err(Error::PayloadError);
+    let response = res
+        .json::<graphql_client::Response<Q::ResponseData>>()
+        .await?;
+
+    if let Some(errors) = response.errors {
+        if !errors.is_empty() {
+            return Err(Error::from_graphql_errors(status, errors));
         }
+    }
 
-        Err(err) => Err(Error::from(err)),
+    match response.data {
+        Some(data) => Ok(data),
+        None => Err(Error::PayloadError(
+            "response carries neither data nor errors".to_owned(),
+        )),
     }
 }
 
-pub async fn get_deck(token: String, id: String) -> Result<Deck, Error> {
-    let client: reqwest::Client;
-    if let Ok(c) = reqwest::Client::builder()
-        .default_headers(
-            std::iter::once((
-                reqwest::header::AUTHORIZATION,
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
-            ))
-            .collect(),
-        )
+/// Whether the backend answers at all; any HTTP response counts.
+pub async fn health_check() -> bool {
+    let client = match reqwest::Client::builder()
+        .timeout(HEALTH_CHECK_TIMEOUT)
         .build()
     {
-        client = c;
-    } else {
-        return Err(Error::NetworkError);
+        Ok(client) => client,
+        Err(_) => return false,
+    };
+
+    client
+        .post(GRAPHQL_URL)
+        .json(&serde_json::json!({ "query": "{ __typename }" }))
+        .send()
+        .await
+        .is_ok()
+}
+
+pub async fn get_decks(token: String, retry: RetryPolicy) -> Result<Vec<Deck>, Error> {
+    let response_body = post_query::<GetPopularDecks>(
+        &token,
+        get_popular_decks::Variables {
+            first: Some(50),
+            search: None,
+        },
+        true,
+        retry,
+    )
+    .await?;
+
+    match response_body.popular_decks {
+        Some(decks_ql) => Ok(decks_ql.into()),
+        None => Err(Error::PayloadError("missing popularDecks".to_owned())),
     }
+}
 
-    let response = post_graphql::<ObtainDeck, _>(
-        &client,
-        GRAPHQL_URL,
-        obtain_deck::Variables { id: id.clone() },
+/// Lists decks other users published, optionally matching `search`.
+pub async fn get_public_decks(
+    token: String,
+    search: Option<String>,
+    retry: RetryPolicy,
+) -> Result<Vec<Deck>, Error> {
+    let response_body = post_query::<GetPublicDecks>(
+        &token,
+        get_public_decks::Variables {
+            first: Some(50),
+            search,
+        },
+        true,
+        retry,
     )
-    .await;
-
-    match response {
-        Ok(response) => {
-            if let Some(response_body) = response.data {
-                if let Some(d_ql) = response_body.deck {
-                    match d_ql.try_into() {
-                        Ok(deck) => return Ok(deck),
-                        Err(err) => return Err(err),
-                    }
-                }
-                return Err(Error::PayloadError);
-            }
-            return Err(Error::PayloadError);
-        }
-        Err(err) => return Err(Error::from(err)),
+    .await?;
+
+    match response_body.public_decks {
+        Some(decks_ql) => Ok(decks_ql.into()),
+        None => Err(Error::PayloadError("missing publicDecks".to_owned())),
     }
 }
 
-pub async fn delete_deck(token: String, deck_id: String) -> Result<reqwest::StatusCode, Error> {
-    let client: reqwest::Client;
-    if let Ok(c) = reqwest::Client::builder()
-        .default_headers(
-            std::iter::once((
-                reqwest::header::AUTHORIZATION,
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
-            ))
-            .collect(),
-        )
-        .build()
-    {
-        client = c;
-    } else {
-        return Err(Error::NetworkError);
+/// Asks Toshokan for decks whose title or description matches `search`,
+/// including those beyond the first page returned by `get_decks`.
+pub async fn search_decks(
+    token: String,
+    search: String,
+    retry: RetryPolicy,
+) -> Result<Vec<Deck>, Error> {
+    let response_body = post_query::<GetPopularDecks>(
+        &token,
+        get_popular_decks::Variables {
+            first: Some(50),
+            search: Some(search),
+        },
+        true,
+        retry,
+    )
+    .await?;
+
+    match response_body.popular_decks {
+        Some(decks_ql) => Ok(decks_ql.into()),
+        None => Err(Error::PayloadError("missing popularDecks".to_owned())),
+    }
+}
+
+pub async fn get_deck(token: String, id: String, retry: RetryPolicy) -> Result<Deck, Error> {
+    let response_body = post_query::<ObtainDeck>(
+        &token,
+        obtain_deck::Variables { id: id.clone() },
+        true,
+        retry,
+    )
+    .await?;
+
+    match response_body.deck {
+        Some(d_ql) => d_ql.try_into(),
+        None => Err(Error::NotFound),
     }
+}
 
-    let response = post_graphql::<RemoveDeck, _>(
-        &client,
-        GRAPHQL_URL,
+pub async fn delete_deck(
+    token: String,
+    deck_id: String,
+    retry: RetryPolicy,
+) -> Result<reqwest::StatusCode, Error> {
+    let response_body = post_query::<RemoveDeck>(
+        &token,
         remove_deck::Variables {
             id: deck_id.clone(),
         },
+        false,
+        retry,
     )
-    .await;
-
-    match response {
-        Ok(response) => {
-            if let Some(response_body) = response.data {
-                if let Some(del_deck) = response_body.delete_deck {
-                    if let Some(_success) = del_deck.success {
-                        return Ok(reqwest::StatusCode::OK);
-                    }
-                    return Err(Error::PayloadError);
-                }
-                return Err(Error::PayloadError);
-            }
-            return Err(Error::PayloadError);
-        }
-        Err(err) => {
-            println!("{:#?}", err);
-            return Err(Error::from(err));
-        }
+    .await?;
+
+    match response_body
+        .delete_deck
+        .and_then(|del_deck| del_deck.success)
+    {
+        Some(true) => Ok(reqwest::StatusCode::OK),
+        Some(false) => Err(Error::APIError {
+            status: reqwest::StatusCode::OK,
+            message: format!("deck {} was not deleted", deck_id),
+        }),
+        None => Err(Error::PayloadError("missing deleteDeck.success".to_owned())),
     }
 }
 
@@ -285,7 +598,9 @@ impl From<Deck> for CreateDeckInput {
         let mut deck_ql = CreateDeckInput {
             title: deck.title.clone(),
             description: deck.description.clone(),
-            is_public: false,
+            folder: Some(deck.folder.clone()),
+            tags: Some(deck.tags.clone()),
+            is_public: deck.is_public,
             cards: vec![],
         };
 
@@ -297,6 +612,7 @@ impl From<Deck> for CreateDeckInput {
                     title: card.title.clone(),
                     answers: vec![],
                     explanation: Some(card.explanation.clone()),
+                    tags: Some(card.tags.clone()),
                 };
 
                 card_ql.answers = card
@@ -315,44 +631,18 @@ impl From<Deck> for CreateDeckInput {
     }
 }
 
-pub async fn create_deck(token: String, deck: Deck) -> Result<Deck, Error> {
-    let client: reqwest::Client;
-    if let Ok(c) = reqwest::Client::builder()
-        .default_headers(
-            std::iter::once((
-                reqwest::header::AUTHORIZATION,
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
-            ))
-            .collect(),
-        )
-        .build()
-    {
-        client = c;
-    } else {
-        return Err(Error::NetworkError);
-    }
-
-    let response = post_graphql::<NewDeck, _>(
-        &client,
-        GRAPHQL_URL,
+pub async fn create_deck(token: String, deck: Deck, retry: RetryPolicy) -> Result<Deck, Error> {
+    let response_body = post_query::<NewDeck>(
+        &token,
         new_deck::Variables { input: deck.into() },
+        false,
+        retry,
     )
-    .await;
-
-    match response {
-        Ok(response) => {
-            if let Some(response_body) = response.data {
-                if let Some(d_ql) = response_body.create_deck {
-                    match d_ql.try_into() {
-                        Ok(deck) => return Ok(deck),
-                        Err(err) => return Err(err),
-                    }
-                }
-                return Err(Error::PayloadError);
-            }
-            return Err(Error::PayloadError);
-        }
-        Err(err) => return Err(Error::from(err)),
+    .await?;
+
+    match response_body.create_deck {
+        Some(d_ql) => d_ql.try_into(),
+        None => Err(Error::PayloadError("missing createDeck".to_owned())),
     }
 }
 
@@ -373,6 +663,13 @@ impl TryFrom<ObtainDeckDeck> for Deck {
             description: d_ql.description.clone(),
             title: d_ql.title.clone(),
             id: d_ql.id.clone(),
+            owner_id: d_ql.owner_id.clone().unwrap_or_default(),
+            folder: d_ql.folder.clone().unwrap_or_default(),
+            tags: d_ql.tags.clone().unwrap_or_default(),
+            card_count: d_ql.card_count.unwrap_or_default().max(0) as usize,
+            created_at: parse_optional_timestamp(d_ql.created_at.as_deref()),
+            is_public: d_ql.is_public.unwrap_or_default(),
+            author: String::new(),
         };
         let c_ql = d_ql.cards.unwrap_or(vec![]);
         for c in c_ql.iter() {
@@ -383,7 +680,10 @@ impl TryFrom<ObtainDeckDeck> for Deck {
             let a_ql = c_card.answers.as_ref().unwrap_or(&ans_placeholder);
 
             if a_ql.len() == 0 {
-                return Err(Error::PayloadError);
+                return Err(Error::PayloadError(format!(
+                    "card {} has no answers",
+                    c_card.id
+                )));
             }
 
             for a in a_ql.iter() {
@@ -394,6 +694,7 @@ impl TryFrom<ObtainDeckDeck> for Deck {
                 });
             }
             deck.cards.push(Card {
+                id: c_card.id.clone(),
                 title: c_card.title.clone(),
                 answers: c_answers,
                 explanation: c_card
@@ -401,8 +702,10 @@ impl TryFrom<ObtainDeckDeck> for Deck {
                     .as_ref()
                     .unwrap_or(&"".to_owned())
                     .clone(),
+                tags: c_card.tags.clone().unwrap_or_default(),
             });
         }
+        deck.card_count = deck.cards.len();
         Ok(deck)
     }
 }
@@ -426,6 +729,55 @@ impl From<GetPopularDecksPopularDecks> for Vec<Deck> {
                 description: d_ql.description.clone(),
                 title: d_ql.title.clone(),
                 id: d_ql.id.clone(),
+                owner_id: d_ql.owner_id.clone().unwrap_or_default(),
+                folder: d_ql.folder.clone().unwrap_or_default(),
+                tags: d_ql.tags.clone().unwrap_or_default(),
+                card_count: d_ql.card_count.unwrap_or_default().max(0) as usize,
+                created_at: parse_optional_timestamp(d_ql.created_at.as_deref()),
+                is_public: d_ql.is_public.unwrap_or_default(),
+                author: String::new(),
+            })
+        }
+        decks
+    }
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "schema.graphql",
+    query_path = "public_decks.graphql",
+    response_derives = "Debug"
+)]
+struct GetPublicDecks;
+
+impl From<GetPublicDecksPublicDecks> for Vec<Deck> {
+    fn from(decks_ql: GetPublicDecksPublicDecks) -> Self {
+        let mut decks: Vec<Deck> = vec![];
+        let edges = decks_ql.edges.unwrap_or(vec![]);
+        for edge in edges.iter() {
+            let d_ql = match edge.node.as_ref() {
+                Some(d_ql) => d_ql,
+                None => continue,
+            };
+            let author = match &d_ql.author {
+                Some(author) => match &author.display_name {
+                    Some(name) if !name.is_empty() => name.clone(),
+                    _ => author.username.clone(),
+                },
+                None => String::new(),
+            };
+            decks.push(Deck {
+                cards: vec![],
+                description: d_ql.description.clone(),
+                title: d_ql.title.clone(),
+                id: d_ql.id.clone(),
+                owner_id: d_ql.owner_id.clone().unwrap_or_default(),
+                folder: d_ql.folder.clone().unwrap_or_default(),
+                tags: d_ql.tags.clone().unwrap_or_default(),
+                card_count: d_ql.card_count.unwrap_or_default().max(0) as usize,
+                created_at: parse_optional_timestamp(d_ql.created_at.as_deref()),
+                is_public: d_ql.is_public.unwrap_or_default(),
+                author,
             })
         }
         decks
@@ -440,13 +792,20 @@ impl TryFrom<NewDeckCreateDeck> for Deck {
         if let Some(deck) = d.deck {
             d_ql = deck;
         } else {
-            return Err(Error::PayloadError);
+            return Err(Error::PayloadError("missing createDeck.deck".to_owned()));
         }
         let mut deck = Deck {
             cards: vec![],
             description: d_ql.description.clone(),
             title: d_ql.title.clone(),
             id: d_ql.id.clone(),
+            owner_id: d_ql.owner_id.clone().unwrap_or_default(),
+            folder: d_ql.folder.clone().unwrap_or_default(),
+            tags: d_ql.tags.clone().unwrap_or_default(),
+            card_count: d_ql.card_count.unwrap_or_default().max(0) as usize,
+            created_at: parse_optional_timestamp(d_ql.created_at.as_deref()),
+            is_public: d_ql.is_public.unwrap_or_default(),
+            author: String::new(),
         };
         let c_ql = d_ql.cards.unwrap_or(vec![]);
         for c in c_ql.iter() {
@@ -457,7 +816,10 @@ impl TryFrom<NewDeckCreateDeck> for Deck {
             let a_ql = c_card.answers.as_ref().unwrap_or(&ans_placeholder);
 
             if a_ql.len() == 0 {
-                return Err(Error::PayloadError);
+                return Err(Error::PayloadError(format!(
+                    "card {} has no answers",
+                    c_card.id
+                )));
             }
 
             for a in a_ql.iter() {
@@ -469,6 +831,7 @@ impl TryFrom<NewDeckCreateDeck> for Deck {
             }
 
             deck.cards.push(Card {
+                id: c_card.id.clone(),
                 title: c_card.title.clone(),
                 answers: c_answers,
                 explanation: c_card
@@ -476,8 +839,10 @@ impl TryFrom<NewDeckCreateDeck> for Deck {
                     .as_ref()
                     .unwrap_or(&"".to_owned())
                     .clone(),
+                tags: c_card.tags.clone().unwrap_or_default(),
             });
         }
+        deck.card_count = deck.cards.len();
         Ok(deck)
     }
 }
@@ -489,3 +854,335 @@ impl TryFrom<NewDeckCreateDeck> for Deck {
     response_derives = "Debug"
 )]
 struct RemoveDeck;
+
+pub async fn get_profile(token: String, retry: RetryPolicy) -> Result<Profile, Error> {
+    let response_body =
+        post_query::<ObtainProfile>(&token, obtain_profile::Variables, true, retry).await?;
+
+    match response_body.profile {
+        Some(p_ql) => Ok(p_ql.into()),
+        None => Err(Error::NotFound),
+    }
+}
+
+pub async fn update_profile(
+    token: String,
+    display_name: String,
+    bio: String,
+    retry: RetryPolicy,
+) -> Result<Profile, Error> {
+    let response_body = post_query::<UpdateProfile>(
+        &token,
+        update_profile::Variables {
+            input: UpdateProfileInput {
+                display_name: Some(display_name),
+                bio: Some(bio),
+            },
+        },
+        false,
+        retry,
+    )
+    .await?;
+
+    match response_body
+        .update_profile
+        .and_then(|updated| updated.profile)
+    {
+        Some(p_ql) => Ok(p_ql.into()),
+        None => Err(Error::PayloadError(
+            "missing updateProfile.profile".to_owned(),
+        )),
+    }
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "schema.graphql",
+    query_path = "obtain_profile.graphql",
+    response_derives = "Debug"
+)]
+struct ObtainProfile;
+
+impl From<ObtainProfileProfile> for Profile {
+    fn from(p_ql: ObtainProfileProfile) -> Self {
+        Profile {
+            id: p_ql.id,
+            username: p_ql.username,
+            display_name: p_ql.display_name.unwrap_or_default(),
+            bio: p_ql.bio.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "schema.graphql",
+    query_path = "update_profile.graphql",
+    response_derives = "Debug"
+)]
+struct UpdateProfile;
+
+impl From<UpdateProfileUpdateProfileProfile> for Profile {
+    fn from(p_ql: UpdateProfileUpdateProfileProfile) -> Self {
+        Profile {
+            id: p_ql.id,
+            username: p_ql.username,
+            display_name: p_ql.display_name.unwrap_or_default(),
+            bio: p_ql.bio.unwrap_or_default(),
+        }
+    }
+}
+
+/// Study progress stored in Toshokan.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub reviews: Vec<Review>,
+    pub card_states: Vec<CardState>,
+    pub synced_at: String,
+}
+
+pub async fn get_progress(
+    token: String,
+    since: Option<String>,
+    retry: RetryPolicy,
+) -> Result<Progress, Error> {
+    let response_body =
+        post_query::<ObtainProgress>(&token, obtain_progress::Variables { since }, true, retry)
+            .await?;
+
+    match response_body.progress {
+        Some(p_ql) => p_ql.try_into(),
+        None => Err(Error::PayloadError("missing progress".to_owned())),
+    }
+}
+
+pub async fn record_progress(
+    token: String,
+    reviews: Vec<Review>,
+    card_states: Vec<CardState>,
+    retry: RetryPolicy,
+) -> Result<(), Error> {
+    let input = RecordProgressInput {
+        reviews: reviews.into_iter().map(|r| r.into()).collect(),
+        card_states: card_states.into_iter().map(|s| s.into()).collect(),
+    };
+    let response_body =
+        post_query::<RecordProgress>(&token, record_progress::Variables { input }, false, retry)
+            .await?;
+
+    match response_body
+        .record_progress
+        .and_then(|recorded| recorded.success)
+    {
+        Some(true) => Ok(()),
+        Some(false) => Err(Error::APIError {
+            status: reqwest::StatusCode::OK,
+            message: "progress was not recorded".to_owned(),
+        }),
+        None => Err(Error::PayloadError(
+            "missing recordProgress.success".to_owned(),
+        )),
+    }
+}
+
+fn parse_optional_timestamp(value: Option<&str>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn parse_timestamp(field: &str, value: &str) -> Result<DateTime<Utc>, Error> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|err| Error::PayloadError(format!("invalid {} {:?}: {}", field, value, err)))
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "schema.graphql",
+    query_path = "obtain_progress.graphql",
+    response_derives = "Debug"
+)]
+struct ObtainProgress;
+
+impl TryFrom<ObtainProgressProgress> for Progress {
+    type Error = Error;
+
+    fn try_from(p_ql: ObtainProgressProgress) -> Result<Self, Self::Error> {
+        Ok(Progress {
+            reviews: p_ql
+                .reviews
+                .into_iter()
+                .map(|r| r.try_into())
+                .collect::<Result<_, _>>()?,
+            card_states: p_ql
+                .card_states
+                .into_iter()
+                .map(|s| s.try_into())
+                .collect::<Result<_, _>>()?,
+            synced_at: p_ql.synced_at,
+        })
+    }
+}
+
+impl TryFrom<ObtainProgressProgressReviews> for Review {
+    type Error = Error;
+
+    fn try_from(r_ql: ObtainProgressProgressReviews) -> Result<Self, Self::Error> {
+        Ok(Review {
+            reviewed_at: parse_timestamp("reviewedAt", &r_ql.reviewed_at)?,
+            id: r_ql.id,
+            deck_id: r_ql.deck_id,
+            card_id: r_ql.card_id,
+            correct: r_ql.correct,
+            response_ms: r_ql.response_ms.map(|ms| ms.max(0) as u64),
+            rating: r_ql.rating.as_deref().and_then(Rating::parse),
+            synced: true,
+        })
+    }
+}
+
+impl TryFrom<ObtainProgressProgressCardStates> for CardState {
+    type Error = Error;
+
+    fn try_from(s_ql: ObtainProgressProgressCardStates) -> Result<Self, Self::Error> {
+        Ok(CardState {
+            due: parse_timestamp("due", &s_ql.due)?,
+            updated_at: parse_timestamp("updatedAt", &s_ql.updated_at)?,
+            deck_id: s_ql.deck_id,
+            card_id: s_ql.card_id,
+            interval_days: s_ql.interval_days as f32,
+            ease: s_ql.ease as f32,
+            repetitions: s_ql.repetitions.max(0) as u32,
+            lapses: s_ql.lapses.max(0) as u32,
+            stability: s_ql.stability.unwrap_or(0.0) as f32,
+            difficulty: s_ql.difficulty.unwrap_or(0.0) as f32,
+            learning_step: s_ql.learning_step.map(|step| step.max(0) as u32),
+            suspended: s_ql.suspended.unwrap_or(false),
+            reviewed_at: parse_optional_timestamp(s_ql.reviewed_at.as_deref()),
+            synced: true,
+        })
+    }
+}
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "schema.graphql",
+    query_path = "record_progress.graphql",
+    response_derives = "Debug"
+)]
+struct RecordProgress;
+
+impl From<Review> for ReviewInput {
+    fn from(review: Review) -> Self {
+        ReviewInput {
+            id: review.id,
+            deck_id: review.deck_id,
+            card_id: review.card_id,
+            reviewed_at: review.reviewed_at.to_rfc3339(),
+            correct: review.correct,
+            response_ms: review.response_ms.map(|ms| ms as i64),
+            rating: review.rating.map(|rating| rating.as_str().to_owned()),
+        }
+    }
+}
+
+impl From<CardState> for CardStateInput {
+    fn from(state: CardState) -> Self {
+        CardStateInput {
+            deck_id: state.deck_id,
+            card_id: state.card_id,
+            due: state.due.to_rfc3339(),
+            interval_days: state.interval_days as f64,
+            ease: state.ease as f64,
+            repetitions: state.repetitions as i64,
+            lapses: state.lapses as i64,
+            stability: Some(state.stability as f64),
+            difficulty: Some(state.difficulty as f64),
+            learning_step: state.learning_step.map(|step| step as i64),
+            suspended: Some(state.suspended),
+            reviewed_at: state.reviewed_at.map(|at| at.to_rfc3339()),
+            updated_at: state.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect_error() -> Error {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        // Nothing listens on port 1.
+        let err = runtime
+            .block_on(reqwest::Client::new().get("http://127.0.0.1:1").send())
+            .unwrap_err();
+        assert!(err.is_connect());
+        err.into()
+    }
+
+    fn request_error() -> Error {
+        let err = reqwest::Client::new()
+            .get("http://[::1")
+            .build()
+            .unwrap_err();
+        assert!(!err.is_connect());
+        err.into()
+    }
+
+    fn status(code: u16) -> Error {
+        Error::APIError {
+            status: reqwest::StatusCode::from_u16(code).unwrap(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn queries_retry_transient_failures() {
+        let policy = RetryPolicy::default();
+        for err in [
+            connect_error(),
+            request_error(),
+            status(500),
+            status(503),
+            status(429),
+        ] {
+            assert!(policy.should_retry(&err, true), "{}", err);
+        }
+        for err in [
+            status(400),
+            status(404),
+            Error::AuthError,
+            Error::NotFound,
+            Error::PayloadError(String::new()),
+        ] {
+            assert!(!policy.should_retry(&err, true), "{}", err);
+        }
+    }
+
+    #[test]
+    fn mutations_only_retry_connect_errors() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(&connect_error(), false));
+        for err in [request_error(), status(500), status(503), status(429)] {
+            assert!(!policy.should_retry(&err, false), "{}", err);
+        }
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap_with_jitter() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..8 {
+            let full = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            for _ in 0..100 {
+                let delay = policy.delay(attempt);
+                assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+            }
+        }
+        // Large attempts saturate rather than overflow.
+        assert!(policy.delay(u32::MAX) <= policy.max_delay);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::client::Deck;
+
+/// Separates the levels of a folder path, as in `Languages::Greek::Verbs`.
+pub const SEPARATOR: &str = "::";
+
+/// A folder of the deck tree together with everything filed below it.
+#[derive(Clone, Debug, Default)]
+pub struct Folder {
+    pub name: String,
+    pub path: Vec<String>,
+    pub folders: Vec<Folder>,
+    /// Indices of the decks filed directly in this folder.
+    pub decks: Vec<usize>,
+}
+
+impl Folder {
+    /// Files the decks at `indices` into a tree whose root is the unnamed
+    /// top level. Decks keep the order they are given in.
+    pub fn tree(decks: &[Deck], indices: &[usize]) -> Folder {
+        let mut root = Folder::default();
+        for index in indices.iter().copied() {
+            root.insert(&decks[index].folder, index);
+        }
+        root.sort();
+        root
+    }
+
+    fn insert(&mut self, path: &[String], index: usize) {
+        let (name, rest) = match path.split_first() {
+            Some(split) => split,
+            None => {
+                self.decks.push(index);
+                return;
+            }
+        };
+
+        let position = match self.folders.iter().position(|f| &f.name == name) {
+            Some(position) => position,
+            None => {
+                let mut path = self.path.clone();
+                path.push(name.clone());
+                self.folders.push(Folder {
+                    name: name.clone(),
+                    path,
+                    ..Folder::default()
+                });
+                self.folders.len() - 1
+            }
+        };
+        self.folders[position].insert(rest, index);
+    }
+
+    fn sort(&mut self) {
+        self.folders.sort_by_cached_key(|f| f.name.to_lowercase());
+        for folder in self.folders.iter_mut() {
+            folder.sort();
+        }
+    }
+
+    /// Number of decks in this folder and all of its subfolders.
+    pub fn deck_count(&self) -> usize {
+        self.decks.len() + self.folders.iter().map(|f| f.deck_count()).sum::<usize>()
+    }
+}
+
+pub fn parse_path(folder: &str) -> Vec<String> {
+    folder
+        .split(SEPARATOR)
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+pub fn format_path(path: &[String]) -> String {
+    path.join(SEPARATOR)
+}
+
+/// Whether a deck filed under `folder` lives somewhere below `path`.
+pub fn is_within(folder: &[String], path: &[String]) -> bool {
+    folder.starts_with(path)
+}
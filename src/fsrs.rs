@@ -0,0 +1,328 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::history::Review;
+use crate::scheduler::{CardState, Rating, Scheduler};
+
+const DECAY: f32 = -0.5;
+/// Makes recall probability drop to 90% once `stability` days have passed.
+const FACTOR: f32 = 19.0 / 81.0;
+const MIN_STABILITY: f32 = 0.01;
+const MIN_DIFFICULTY: f32 = 1.0;
+const MAX_DIFFICULTY: f32 = 10.0;
+/// Predictions needed before fitting says anything about the learner.
+pub const MIN_FIT_REVIEWS: usize = 50;
+const FIT_PASSES: usize = 8;
+
+/// FSRS-4.5 weights, trained on a large public collection of reviews.
+pub const DEFAULT_WEIGHTS: [f32; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+const WEIGHT_BOUNDS: [(f32, f32); 17] = [
+    (0.01, 100.0),
+    (0.01, 100.0),
+    (0.01, 100.0),
+    (0.01, 100.0),
+    (1.0, 10.0),
+    (0.1, 5.0),
+    (0.1, 5.0),
+    (0.0, 0.75),
+    (0.0, 4.0),
+    (0.0, 0.8),
+    (0.01, 3.0),
+    (0.5, 5.0),
+    (0.01, 0.2),
+    (0.01, 0.9),
+    (0.01, 3.0),
+    (0.0, 1.0),
+    (1.0, 6.0),
+];
+
+/// Free Spaced Repetition Scheduler. Models how stable a card's memory is
+/// and how difficult the card is, and schedules the next review for when
+/// recall probability falls to the desired retention.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Fsrs {
+    pub weights: [f32; 17],
+    pub desired_retention: f32,
+}
+
+/// What FSRS knows about the memory of a card after its latest review.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Memory {
+    /// Days until recall probability falls to 90%.
+    pub stability: f32,
+    pub difficulty: f32,
+}
+
+/// Weights fitted to a learner's reviews, with the log loss of the recall
+/// predictions before and after fitting.
+#[derive(Clone, Debug)]
+pub struct Fit {
+    pub fsrs: Fsrs,
+    pub reviews: usize,
+    pub initial_loss: f32,
+    pub loss: f32,
+}
+
+impl Default for Fsrs {
+    fn default() -> Self {
+        Fsrs {
+            weights: DEFAULT_WEIGHTS,
+            desired_retention: 0.9,
+        }
+    }
+}
+
+fn grade(rating: Rating) -> f32 {
+    match rating {
+        Rating::Again => 1.0,
+        Rating::Hard => 2.0,
+        Rating::Good => 3.0,
+        Rating::Easy => 4.0,
+    }
+}
+
+/// The rating a review stands for, for reviews recorded before ratings.
+pub fn review_rating(review: &Review) -> Rating {
+    review
+        .rating
+        .unwrap_or_else(|| Rating::from_correct(review.correct))
+}
+
+impl Fsrs {
+    /// Probability of recalling a card `elapsed_days` after its last review.
+    pub fn retrievability(&self, elapsed_days: f32, stability: f32) -> f32 {
+        (1.0 + FACTOR * elapsed_days.max(0.0) / stability.max(MIN_STABILITY)).powf(DECAY)
+    }
+
+    /// Days until recall probability falls to the desired retention.
+    pub fn interval(&self, stability: f32) -> f32 {
+        let interval = stability / FACTOR * (self.desired_retention.powf(1.0 / DECAY) - 1.0);
+        interval.round().max(1.0)
+    }
+
+    fn initial_difficulty(&self, rating: Rating) -> f32 {
+        let w = &self.weights;
+        (w[4] - (grade(rating) - 3.0) * w[5]).clamp(MIN_DIFFICULTY, MAX_DIFFICULTY)
+    }
+
+    /// Memory after a review `elapsed_days` after the previous one, or after
+    /// the first review when there is no memory yet.
+    pub fn next_memory(&self, memory: Option<Memory>, elapsed_days: f32, rating: Rating) -> Memory {
+        let w = &self.weights;
+        let memory = match memory {
+            Some(memory) => memory,
+            None => {
+                return Memory {
+                    stability: w[grade(rating) as usize - 1].max(MIN_STABILITY),
+                    difficulty: self.initial_difficulty(rating),
+                }
+            }
+        };
+
+        let Memory {
+            stability,
+            difficulty,
+        } = memory;
+        let recall = self.retrievability(elapsed_days, stability);
+
+        let stability = if rating.is_recalled() {
+            let hard_penalty = if rating == Rating::Hard { w[15] } else { 1.0 };
+            let easy_bonus = if rating == Rating::Easy { w[16] } else { 1.0 };
+            stability
+                * (w[8].exp()
+                    * (11.0 - difficulty)
+                    * stability.powf(-w[9])
+                    * ((w[10] * (1.0 - recall)).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus
+                    + 1.0)
+        } else {
+            (w[11]
+                * difficulty.powf(-w[12])
+                * ((stability + 1.0).powf(w[13]) - 1.0)
+                * (w[14] * (1.0 - recall)).exp())
+            .min(stability)
+        };
+
+        let difficulty = difficulty - w[6] * (grade(rating) - 3.0);
+        let difficulty = w[7] * self.initial_difficulty(Rating::Good) + (1.0 - w[7]) * difficulty;
+
+        Memory {
+            stability: stability.max(MIN_STABILITY),
+            difficulty: difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY),
+        }
+    }
+
+    /// Memory held in a card state. Cards last scheduled by SM-2 start from
+    /// their interval as stability.
+    fn memory(&self, state: &CardState) -> Option<Memory> {
+        if state.stability > 0.0 {
+            Some(Memory {
+                stability: state.stability,
+                difficulty: state.difficulty,
+            })
+        } else if state.repetitions > 0 || state.lapses > 0 {
+            Some(Memory {
+                stability: state.interval_days.max(MIN_STABILITY),
+                difficulty: self.initial_difficulty(Rating::Good),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Tunes the weights to the learner's own reviews by coordinate descent
+    /// on the log loss of predicted recall. Gives up when the reviews hold
+    /// too few repeated cards to learn from.
+    pub fn fit(reviews: &[Review]) -> Option<Fit> {
+        let sequences = review_sequences(reviews);
+        let mut best = Fsrs::default();
+        let (initial_loss, predictions) = best.loss(&sequences);
+        if predictions < MIN_FIT_REVIEWS {
+            return None;
+        }
+
+        let mut loss = initial_loss;
+        let mut step = 0.2;
+        for _ in 0..FIT_PASSES {
+            for (index, (lower, upper)) in WEIGHT_BOUNDS.iter().copied().enumerate() {
+                for direction in [1.0, -1.0] {
+                    let mut candidate = best.clone();
+                    let weight = candidate.weights[index];
+                    candidate.weights[index] =
+                        (weight + direction * step * weight.abs().max(0.1)).clamp(lower, upper);
+
+                    let (candidate_loss, _) = candidate.loss(&sequences);
+                    if candidate_loss < loss {
+                        best = candidate;
+                        loss = candidate_loss;
+                        break;
+                    }
+                }
+            }
+            step *= 0.7;
+        }
+
+        Some(Fit {
+            fsrs: best,
+            reviews: predictions,
+            initial_loss,
+            loss,
+        })
+    }
+
+    /// Mean log loss of predicting each review from the ones before it,
+    /// along with how many reviews were predicted.
+    fn loss(&self, sequences: &[Vec<&Review>]) -> (f32, usize) {
+        let mut total = 0.0;
+        let mut predictions = 0;
+
+        for sequence in sequences {
+            let mut memory = None;
+            let mut last_review: Option<DateTime<Utc>> = None;
+            for review in sequence {
+                let rating = review_rating(review);
+                let elapsed =
+                    last_review.map_or(0.0, |last| days_between(last, review.reviewed_at));
+                if let Some(Memory { stability, .. }) = memory {
+                    let recall = self
+                        .retrievability(elapsed, stability)
+                        .clamp(0.0001, 0.9999);
+                    total -= if rating.is_recalled() {
+                        recall.ln()
+                    } else {
+                        (1.0 - recall).ln()
+                    };
+                    predictions += 1;
+                }
+                memory = Some(self.next_memory(memory, elapsed, rating));
+                last_review = Some(review.reviewed_at);
+            }
+        }
+
+        (total / predictions.max(1) as f32, predictions)
+    }
+}
+
+impl Scheduler for Fsrs {
+    fn schedule(&self, state: &mut CardState, rating: Rating, now: DateTime<Utc>) -> f32 {
+        let memory = self.next_memory(self.memory(state), state.elapsed_days(now), rating);
+        state.stability = memory.stability;
+        state.difficulty = memory.difficulty;
+        self.interval(memory.stability)
+    }
+}
+
+pub fn days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f32 {
+    (to - from).num_seconds().max(0) as f32 / 86_400.0
+}
+
+/// Reviews grouped per card, oldest first.
+pub fn review_sequences(reviews: &[Review]) -> Vec<Vec<&Review>> {
+    let mut cards: HashMap<&str, Vec<&Review>> = HashMap::new();
+    for review in reviews {
+        cards.entry(&review.card_id).or_default().push(review);
+    }
+
+    let mut sequences: Vec<Vec<&Review>> = cards.into_values().collect();
+    for sequence in sequences.iter_mut() {
+        sequence.sort_by_key(|review| review.reviewed_at);
+    }
+    sequences.sort_by_key(|sequence| sequence[0].reviewed_at);
+    sequences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    /// `reviews_per_card` reviews of each card at growing gaps, with a
+    /// lapse every few reviews.
+    fn reviews(cards: usize, reviews_per_card: usize) -> Vec<Review> {
+        let start = Utc.with_ymd_and_hms(2023, 1, 1, 9, 0, 0).unwrap();
+        let mut reviews = Vec::new();
+        for card in 0..cards {
+            let mut reviewed_at = start;
+            for review in 0..reviews_per_card {
+                reviewed_at += Duration::days(1 << review);
+                reviews.push(Review {
+                    id: format!("{}-{}", card, review),
+                    deck_id: "deck".to_owned(),
+                    card_id: format!("card-{}", card),
+                    reviewed_at,
+                    correct: (card + review) % 4 != 0,
+                    response_ms: None,
+                    rating: None,
+                    synced: false,
+                });
+            }
+        }
+        reviews
+    }
+
+    #[test]
+    fn fit_needs_enough_predictions() {
+        // The first review of each card predicts nothing.
+        let reviews = reviews(MIN_FIT_REVIEWS / 2 - 1, 3);
+        assert!(Fsrs::fit(&reviews).is_none());
+    }
+
+    #[test]
+    fn fit_never_increases_the_loss() {
+        let reviews = reviews(30, 5);
+        let fit = Fsrs::fit(&reviews).expect("enough reviews to fit");
+
+        assert_eq!(fit.reviews, 30 * 4);
+        assert!(fit.loss <= fit.initial_loss);
+        let (loss, _) = fit.fsrs.loss(&review_sequences(&reviews));
+        assert!((loss - fit.loss).abs() < 1e-6);
+        let (default_loss, _) = Fsrs::default().loss(&review_sequences(&reviews));
+        assert!(loss <= default_loss);
+    }
+}
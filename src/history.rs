@@ -0,0 +1,910 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::client::{Card, Deck};
+use crate::preferences::{DeckSettings, LeechAction};
+use crate::scheduler::{CardState, Rating, Scheduler};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    #[serde(default)]
+    pub rounds: Vec<RoundRecord>,
+    #[serde(default)]
+    pub reviews: Vec<Review>,
+    /// Scheduling state per card id.
+    #[serde(default)]
+    pub card_states: HashMap<String, CardState>,
+    /// Cursor returned by the last progress download from Toshokan.
+    #[serde(default)]
+    pub synced_at: Option<String>,
+    #[serde(default)]
+    pub exam_attempts: Vec<ExamAttempt>,
+    #[serde(default)]
+    pub undone_answers: Vec<UndoneAnswer>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoundRecord {
+    pub deck_id: String,
+    pub deck_title: String,
+    pub finished_at: DateTime<Utc>,
+    pub cards: usize,
+    pub correct_cards: usize,
+}
+
+/// A finished exam. Exams test rather than teach, so they leave the card
+/// schedules alone.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExamAttempt {
+    pub deck_id: String,
+    pub deck_title: String,
+    pub finished_at: DateTime<Utc>,
+    pub cards: usize,
+    pub correct_cards: usize,
+    pub time_taken_ms: u64,
+    /// The limit as shown to the learner, such as `10:00 overall`.
+    pub time_limit: String,
+}
+
+impl ExamAttempt {
+    pub fn score(&self) -> f32 {
+        self.correct_cards as f32 / self.cards.max(1) as f32 * 100.0
+    }
+}
+
+/// A single answered card. Reviews are never modified once recorded, so
+/// machines merge them by id.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Review {
+    #[serde(default = "review_id")]
+    pub id: String,
+    pub deck_id: String,
+    pub card_id: String,
+    pub reviewed_at: DateTime<Utc>,
+    pub correct: bool,
+    /// Time from showing the card to answering it.
+    #[serde(default)]
+    pub response_ms: Option<u64>,
+    /// Self-graded recall; reviews from before ratings existed have none.
+    #[serde(default)]
+    pub rating: Option<Rating>,
+    #[serde(default)]
+    pub synced: bool,
+}
+
+/// An answer taken back during a round, before the round scheduled it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UndoneAnswer {
+    pub deck_id: String,
+    pub card_id: String,
+    /// Whether the answer taken back was right.
+    pub correct: bool,
+    pub undone_at: DateTime<Utc>,
+}
+
+/// How a card was answered during a round.
+#[derive(Clone, Debug)]
+pub struct AnsweredCard {
+    pub card_id: String,
+    pub correct: bool,
+    pub response_ms: Option<u64>,
+    pub rating: Rating,
+}
+
+pub fn review_id() -> String {
+    format!("{:032x}", rand::thread_rng().gen::<u128>())
+}
+
+/// Cards whose interval reached this many days count as mature.
+pub const MATURE_INTERVAL_DAYS: f32 = 21.0;
+pub const FORECAST_DAYS: usize = 30;
+
+#[derive(Clone, Debug, Default)]
+pub struct DeckStatistics {
+    /// Share of correct answers per day the deck was studied, oldest first.
+    pub accuracy_by_day: Vec<(NaiveDate, f32)>,
+    pub new_cards: usize,
+    pub learning_cards: usize,
+    pub mature_cards: usize,
+    /// Card titles with their lapse count, most lapses first.
+    pub hardest_cards: Vec<(String, u32)>,
+    pub average_response_ms: Option<u64>,
+    /// Reviews falling due on each of the next days, overdue ones on today.
+    pub forecast: Vec<usize>,
+    pub undone_answers: usize,
+}
+
+/// What a deck holds to study today once the daily limits are applied.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DueCounts {
+    /// Cards in their learning steps or due for review.
+    pub due: usize,
+    pub new: usize,
+}
+
+impl DueCounts {
+    /// Cards a round would hold today.
+    pub fn total(&self) -> usize {
+        self.due + self.new
+    }
+}
+
+/// Runs of consecutive days with at least one review.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Streaks {
+    /// Days in the run reaching today, or yesterday while today is still open.
+    pub current: usize,
+    pub longest: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    pub rounds: usize,
+    pub reviews: usize,
+    pub correct_reviews: usize,
+    pub decks_studied: usize,
+    pub cards_studied: usize,
+    pub average_score: f32,
+    pub last_studied: Option<DateTime<Utc>>,
+}
+
+impl Statistics {
+    pub fn accuracy(&self) -> f32 {
+        if self.reviews == 0 {
+            return 0.0;
+        }
+        self.correct_reviews as f32 / self.reviews as f32 * 100.0
+    }
+}
+
+impl History {
+    /// Records a finished round and reschedules its cards. Returns the ids
+    /// of the cards that became leeches with it; they are already suspended
+    /// when the deck settings ask for that.
+    pub fn record_round(
+        &mut self,
+        deck: &Deck,
+        answers: &[AnsweredCard],
+        scheduler: &dyn Scheduler,
+        settings: &DeckSettings,
+    ) -> Vec<String> {
+        let now = Utc::now();
+        let learning_steps = settings.learning_steps();
+        let mut leeches = Vec::new();
+
+        for answer in answers {
+            self.reviews.push(Review {
+                id: review_id(),
+                deck_id: deck.id.clone(),
+                card_id: answer.card_id.clone(),
+                reviewed_at: now,
+                correct: answer.correct,
+                response_ms: answer.response_ms,
+                rating: Some(answer.rating),
+                synced: false,
+            });
+            let state = self
+                .card_states
+                .entry(answer.card_id.clone())
+                .or_insert_with(|| CardState::new(&deck.id, &answer.card_id, now));
+            let was_leech = settings.is_leech(state.lapses);
+            state.review(scheduler, &learning_steps, answer.rating, now);
+            if !was_leech && settings.is_leech(state.lapses) {
+                if settings.leech_action == LeechAction::Suspend {
+                    state.set_suspended(true, now);
+                }
+                leeches.push(answer.card_id.clone());
+            }
+        }
+
+        self.rounds.push(RoundRecord {
+            deck_id: deck.id.clone(),
+            deck_title: deck.title.clone(),
+            finished_at: now,
+            cards: answers.len(),
+            correct_cards: answers.iter().filter(|a| a.correct).count(),
+        });
+        leeches
+    }
+
+    pub fn record_undo(&mut self, deck_id: &str, card_id: &str, correct: bool) {
+        self.undone_answers.push(UndoneAnswer {
+            deck_id: deck_id.to_owned(),
+            card_id: card_id.to_owned(),
+            correct,
+            undone_at: Utc::now(),
+        });
+    }
+
+    pub fn record_exam(&mut self, attempt: ExamAttempt) {
+        self.exam_attempts.push(attempt);
+    }
+
+    /// Exam attempts at the deck, latest first.
+    pub fn exam_attempts(&self, deck_id: &str) -> Vec<&ExamAttempt> {
+        let mut attempts: Vec<&ExamAttempt> = self
+            .exam_attempts
+            .iter()
+            .filter(|attempt| attempt.deck_id == deck_id)
+            .collect();
+        attempts.sort_by(|a, b| b.finished_at.cmp(&a.finished_at));
+        attempts
+    }
+
+    pub fn is_suspended(&self, card_id: &str) -> bool {
+        self.card_states
+            .get(card_id)
+            .map_or(false, |state| state.suspended)
+    }
+
+    pub fn set_suspended(&mut self, deck_id: &str, card_id: &str, suspended: bool) {
+        let now = Utc::now();
+        self.card_states
+            .entry(card_id.to_owned())
+            .or_insert_with(|| CardState::new(deck_id, card_id, now))
+            .set_suspended(suspended, now);
+    }
+
+    /// Cards of the deck that lapsed at least as often as its leech
+    /// threshold, with their lapse count, most lapses first.
+    pub fn leeches<'a>(&self, deck: &'a Deck, settings: &DeckSettings) -> Vec<(&'a Card, u32)> {
+        let mut leeches: Vec<(&Card, u32)> = deck
+            .cards
+            .iter()
+            .filter_map(|card| {
+                let lapses = self.card_states.get(&card.id)?.lapses;
+                settings.is_leech(lapses).then_some((card, lapses))
+            })
+            .collect();
+        leeches.sort_by(|a, b| b.1.cmp(&a.1));
+        leeches
+    }
+
+    /// Editing a deck recreates it server-side, so every card gets a new id.
+    /// `sent` is the deck as submitted (still carrying the old ids) and
+    /// `created` the server's answer, whose cards come back in the same order.
+    pub fn rekey_deck(&mut self, old_deck_id: &str, sent: &Deck, created: &Deck) {
+        let mut card_ids: HashMap<&str, &str> = HashMap::new();
+        for (old, new) in sent.cards.iter().zip(created.cards.iter()) {
+            if !old.id.is_empty() {
+                card_ids.insert(&old.id, &new.id);
+            }
+        }
+
+        for review in self.reviews.iter_mut() {
+            if review.deck_id != old_deck_id {
+                continue;
+            }
+            review.deck_id = created.id.clone();
+            review.synced = false;
+            if let Some(new_id) = card_ids.get(review.card_id.as_str()) {
+                review.card_id = new_id.to_string();
+            }
+        }
+
+        let moved: Vec<String> = self
+            .card_states
+            .values()
+            .filter(|state| state.deck_id == old_deck_id)
+            .map(|state| state.card_id.clone())
+            .collect();
+        for card_id in moved {
+            let mut state = self.card_states.remove(&card_id).unwrap();
+            state.deck_id = created.id.clone();
+            state.synced = false;
+            if let Some(new_id) = card_ids.get(card_id.as_str()) {
+                state.card_id = new_id.to_string();
+            }
+            self.card_states.insert(state.card_id.clone(), state);
+        }
+
+        for round in self.rounds.iter_mut() {
+            if round.deck_id == old_deck_id {
+                round.deck_id = created.id.clone();
+                round.deck_title = created.title.clone();
+            }
+        }
+    }
+
+    /// Reviews and card states Toshokan has not seen yet, leaving out decks
+    /// for which `skip_deck` returns true.
+    pub fn unsynced(&self, skip_deck: impl Fn(&str) -> bool) -> (Vec<Review>, Vec<CardState>) {
+        let reviews = self
+            .reviews
+            .iter()
+            .filter(|r| !r.synced && !skip_deck(&r.deck_id))
+            .cloned()
+            .collect();
+        let card_states = self
+            .card_states
+            .values()
+            .filter(|s| !s.synced && !skip_deck(&s.deck_id))
+            .cloned()
+            .collect();
+
+        (reviews, card_states)
+    }
+
+    /// Flags uploaded data as synced. A card state changed again while the
+    /// upload was running keeps waiting for the next one.
+    pub fn mark_synced(&mut self, reviews: &[Review], card_states: &[CardState]) {
+        let sent: HashSet<(&str, &str)> = reviews
+            .iter()
+            .map(|r| (r.id.as_str(), r.card_id.as_str()))
+            .collect();
+        for review in self.reviews.iter_mut() {
+            if sent.contains(&(review.id.as_str(), review.card_id.as_str())) {
+                review.synced = true;
+            }
+        }
+
+        for sent in card_states {
+            if let Some(state) = self.card_states.get_mut(&sent.card_id) {
+                if state.updated_at == sent.updated_at {
+                    state.synced = true;
+                }
+            }
+        }
+    }
+
+    /// Folds in progress recorded on other machines: reviews are merged by
+    /// id, and for card states the most recently updated one wins.
+    pub fn merge(&mut self, reviews: Vec<Review>, card_states: Vec<CardState>) {
+        let known: HashSet<String> = self.reviews.iter().map(|r| r.id.clone()).collect();
+        for review in reviews {
+            if !known.contains(&review.id) {
+                self.reviews.push(Review {
+                    synced: true,
+                    ..review
+                });
+            }
+        }
+        self.reviews.sort_by_key(|r| r.reviewed_at);
+
+        for remote in card_states {
+            let newer = match self.card_states.get(&remote.card_id) {
+                Some(local) => remote.updated_at > local.updated_at,
+                None => true,
+            };
+            if newer {
+                self.card_states.insert(
+                    remote.card_id.clone(),
+                    CardState {
+                        synced: true,
+                        ..remote
+                    },
+                );
+            }
+        }
+    }
+
+    /// Cards of the deck first seen today, and reviews today of cards seen
+    /// before. Both count against the deck's daily limits.
+    fn studied_today(&self, deck_id: &str, now: DateTime<Utc>) -> (usize, usize) {
+        let today = now.with_timezone(&Local).date_naive();
+        let mut first_seen: HashMap<&str, NaiveDate> = HashMap::new();
+        for review in self.reviews.iter().filter(|r| r.deck_id == deck_id) {
+            let day = review.reviewed_at.with_timezone(&Local).date_naive();
+            let first = first_seen.entry(&review.card_id).or_insert(day);
+            *first = (*first).min(day);
+        }
+
+        let new = first_seen.values().filter(|day| **day == today).count();
+        let reviews = self
+            .reviews
+            .iter()
+            .filter(|r| r.deck_id == deck_id)
+            .filter(|r| r.reviewed_at.with_timezone(&Local).date_naive() == today)
+            .filter(|r| first_seen[r.card_id.as_str()] < today)
+            .count();
+        (new, reviews)
+    }
+
+    /// Indices of the deck cards a round should hold now: cards in their
+    /// learning steps, then due reviews, most overdue first, then new cards
+    /// in deck order, as far as the daily limits allow. Only cards passing
+    /// `include` are considered.
+    pub fn scheduled_cards(
+        &self,
+        deck: &Deck,
+        settings: &DeckSettings,
+        now: DateTime<Utc>,
+        include: impl Fn(&Card) -> bool,
+    ) -> Vec<usize> {
+        let mut learning = Vec::new();
+        let mut due = Vec::new();
+        let mut new = Vec::new();
+        for (index, card) in deck.cards.iter().enumerate() {
+            if !include(card) || self.is_suspended(&card.id) {
+                continue;
+            }
+            match self.card_states.get(&card.id) {
+                Some(state) if state.is_learning() => {
+                    if state.is_due(now) {
+                        learning.push(index);
+                    }
+                }
+                Some(state) if !state.is_new() => {
+                    if state.is_due(now) {
+                        due.push((index, state.due));
+                    }
+                }
+                _ => new.push(index),
+            }
+        }
+        due.sort_by_key(|(_, due)| *due);
+
+        let (new_today, reviews_today) = self.studied_today(&deck.id, now);
+        let reviews_left = settings.reviews_per_day.saturating_sub(reviews_today);
+        let new_left = settings.new_cards_per_day.saturating_sub(new_today);
+
+        learning
+            .into_iter()
+            .chain(due.into_iter().map(|(index, _)| index).take(reviews_left))
+            .chain(new.into_iter().take(new_left))
+            .collect()
+    }
+
+    /// Due and new cards of the deck for the deck list. Decks whose cards
+    /// are not loaded yet count every card without a state as new.
+    pub fn due_counts(
+        &self,
+        deck: &Deck,
+        settings: &DeckSettings,
+        now: DateTime<Utc>,
+    ) -> DueCounts {
+        if !deck.cards.is_empty() {
+            let mut counts = DueCounts::default();
+            for index in self.scheduled_cards(deck, settings, now, |_| true) {
+                match self.card_states.get(&deck.cards[index].id) {
+                    Some(state) if !state.is_new() || state.is_learning() => counts.due += 1,
+                    _ => counts.new += 1,
+                }
+            }
+            return counts;
+        }
+
+        let states: Vec<&CardState> = self
+            .card_states
+            .values()
+            .filter(|state| state.deck_id == deck.id)
+            .collect();
+        let (new_today, reviews_today) = self.studied_today(&deck.id, now);
+        let learning = states
+            .iter()
+            .filter(|s| s.is_learning() && !s.suspended && s.is_due(now))
+            .count();
+        let reviews = states
+            .iter()
+            .filter(|s| !s.is_new() && !s.is_learning() && !s.suspended && s.is_due(now))
+            .count();
+        let unseen = deck.card_count.saturating_sub(states.len());
+
+        DueCounts {
+            due: learning + reviews.min(settings.reviews_per_day.saturating_sub(reviews_today)),
+            new: unseen.min(settings.new_cards_per_day.saturating_sub(new_today)),
+        }
+    }
+
+    pub fn last_studied(&self, deck_id: &str) -> Option<DateTime<Utc>> {
+        self.rounds
+            .iter()
+            .filter(|round| round.deck_id == deck_id)
+            .map(|round| round.finished_at)
+            .max()
+    }
+
+    /// Statistics of the deck, with reviews and due dates counted by local
+    /// day like the rest of the history.
+    pub fn deck_statistics(&self, deck: &Deck, now: DateTime<Utc>) -> DeckStatistics {
+        let today = now.with_timezone(&Local).date_naive();
+        let reviews: Vec<&Review> = self
+            .reviews
+            .iter()
+            .filter(|r| r.deck_id == deck.id)
+            .collect();
+
+        let mut days: Vec<(NaiveDate, usize, usize)> = Vec::new();
+        for review in reviews.iter() {
+            let day = review.reviewed_at.with_timezone(&Local).date_naive();
+            match days.iter_mut().find(|(d, _, _)| *d == day) {
+                Some((_, total, correct)) => {
+                    *total += 1;
+                    *correct += review.correct as usize;
+                }
+                None => days.push((day, 1, review.correct as usize)),
+            }
+        }
+        days.sort_by_key(|(day, _, _)| *day);
+
+        let mut statistics = DeckStatistics {
+            accuracy_by_day: days
+                .into_iter()
+                .map(|(day, total, correct)| (day, correct as f32 / total as f32 * 100.0))
+                .collect(),
+            forecast: vec![0; FORECAST_DAYS],
+            undone_answers: self
+                .undone_answers
+                .iter()
+                .filter(|undone| undone.deck_id == deck.id)
+                .count(),
+            ..DeckStatistics::default()
+        };
+
+        for card in deck.cards.iter() {
+            let state = match self.card_states.get(&card.id) {
+                Some(state) if state.repetitions > 0 || state.lapses > 0 => state,
+                _ => {
+                    statistics.new_cards += 1;
+                    continue;
+                }
+            };
+
+            if state.interval_days >= MATURE_INTERVAL_DAYS {
+                statistics.mature_cards += 1;
+            } else {
+                statistics.learning_cards += 1;
+            }
+            if state.lapses > 0 {
+                statistics
+                    .hardest_cards
+                    .push((card.title.clone(), state.lapses));
+            }
+
+            let due_day = state.due.with_timezone(&Local).date_naive();
+            let day = (due_day - today).num_days().max(0) as usize;
+            if day < FORECAST_DAYS {
+                statistics.forecast[day] += 1;
+            }
+        }
+        statistics
+            .hardest_cards
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let timed: Vec<u64> = reviews.iter().filter_map(|r| r.response_ms).collect();
+        if !timed.is_empty() {
+            statistics.average_response_ms = Some(timed.iter().sum::<u64>() / timed.len() as u64);
+        }
+
+        statistics
+    }
+
+    /// Number of reviews on each local calendar day.
+    pub fn reviews_per_day(&self) -> HashMap<NaiveDate, usize> {
+        let mut days = HashMap::new();
+        for review in self.reviews.iter() {
+            *days
+                .entry(review.reviewed_at.with_timezone(&Local).date_naive())
+                .or_insert(0) += 1;
+        }
+        days
+    }
+
+    pub fn streaks(&self, today: NaiveDate) -> Streaks {
+        let days = self.reviews_per_day();
+        let mut studied: Vec<NaiveDate> = days.into_keys().collect();
+        studied.sort();
+
+        let mut streaks = Streaks::default();
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for day in studied.iter().copied() {
+            run = match previous {
+                Some(previous) if day - previous == Duration::days(1) => run + 1,
+                _ => 1,
+            };
+            streaks.longest = streaks.longest.max(run);
+            previous = Some(day);
+        }
+
+        if let Some(last) = previous {
+            if last == today || last == today - Duration::days(1) {
+                streaks.current = run;
+            }
+        }
+        streaks
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        let decks: HashSet<&str> = self.rounds.iter().map(|r| r.deck_id.as_str()).collect();
+        let cards: HashSet<&str> = self.reviews.iter().map(|r| r.card_id.as_str()).collect();
+
+        let average_score = if self.rounds.is_empty() {
+            0.0
+        } else {
+            self.rounds
+                .iter()
+                .map(|r| r.correct_cards as f32 / r.cards.max(1) as f32 * 100.0)
+                .sum::<f32>()
+                / self.rounds.len() as f32
+        };
+
+        Statistics {
+            rounds: self.rounds.len(),
+            reviews: self.reviews.len(),
+            correct_reviews: self.reviews.iter().filter(|r| r.correct).count(),
+            decks_studied: decks.len(),
+            cards_studied: cards.len(),
+            average_score,
+            last_studied: self.rounds.iter().map(|r| r.finished_at).max(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Sm2;
+
+    /// Noon today, so that whole days either side stay on their local day.
+    fn noon() -> DateTime<Utc> {
+        Local::now()
+            .date_naive()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn card(index: usize) -> Card {
+        Card {
+            id: format!("card{}", index),
+            title: String::new(),
+            answers: Vec::new(),
+            explanation: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    fn deck(cards: usize) -> Deck {
+        Deck {
+            cards: (0..cards).map(card).collect(),
+            owner_id: String::new(),
+            folder: Vec::new(),
+            tags: Vec::new(),
+            card_count: cards,
+            created_at: None,
+            is_public: false,
+            author: String::new(),
+            description: String::new(),
+            title: String::new(),
+            id: "deck".to_owned(),
+        }
+    }
+
+    fn review(deck_id: &str, card: usize, reviewed_at: DateTime<Utc>) -> Review {
+        Review {
+            id: review_id(),
+            deck_id: deck_id.to_owned(),
+            card_id: format!("card{}", card),
+            reviewed_at,
+            correct: true,
+            response_ms: None,
+            rating: None,
+            synced: false,
+        }
+    }
+
+    /// A card out of its learning steps, due at `due`.
+    fn reviewing(card: usize, due: DateTime<Utc>) -> CardState {
+        CardState {
+            repetitions: 2,
+            interval_days: 6.0,
+            ..CardState::new("deck", &format!("card{}", card), due)
+        }
+    }
+
+    fn learning(card: usize, due: DateTime<Utc>) -> CardState {
+        CardState {
+            learning_step: Some(1),
+            ..CardState::new("deck", &format!("card{}", card), due)
+        }
+    }
+
+    fn history(states: Vec<CardState>) -> History {
+        History {
+            card_states: states
+                .into_iter()
+                .map(|state| (state.card_id.clone(), state))
+                .collect(),
+            ..History::default()
+        }
+    }
+
+    #[test]
+    fn studied_today_splits_new_cards_from_reviews() {
+        let now = noon();
+        let yesterday = now - Duration::days(1);
+        let history = History {
+            reviews: vec![
+                // Seen before, reviewed twice today.
+                review("deck", 0, yesterday),
+                review("deck", 0, now - Duration::hours(2)),
+                review("deck", 0, now - Duration::hours(1)),
+                // First seen today, whatever the number of reviews.
+                review("deck", 1, now - Duration::hours(2)),
+                review("deck", 1, now - Duration::hours(1)),
+                // Only seen yesterday.
+                review("deck", 2, yesterday),
+                review("other", 3, now),
+            ],
+            ..History::default()
+        };
+
+        assert_eq!(history.studied_today("deck", now), (1, 2));
+        assert_eq!(history.studied_today("other", now), (1, 0));
+    }
+
+    #[test]
+    fn scheduled_cards_put_learning_then_overdue_then_new() {
+        let now = noon();
+        let mut history = history(vec![
+            reviewing(1, now - Duration::days(1)),
+            reviewing(2, now - Duration::days(3)),
+            learning(3, now - Duration::minutes(5)),
+            reviewing(4, now + Duration::days(1)),
+            learning(5, now + Duration::minutes(5)),
+        ]);
+        history.set_suspended("deck", "card6", true);
+
+        let scheduled = history.scheduled_cards(&deck(7), &DeckSettings::default(), now, |_| true);
+        assert_eq!(scheduled, vec![3, 2, 1, 0]);
+
+        let only_reviews =
+            history.scheduled_cards(&deck(7), &DeckSettings::default(), now, |card| {
+                card.id != "card0" && card.id != "card3"
+            });
+        assert_eq!(only_reviews, vec![2, 1]);
+    }
+
+    #[test]
+    fn scheduled_cards_respect_the_daily_limits() {
+        let now = noon();
+        let settings = DeckSettings {
+            new_cards_per_day: 2,
+            reviews_per_day: 1,
+            ..DeckSettings::default()
+        };
+        let mut history = history(vec![
+            reviewing(0, now - Duration::days(1)),
+            reviewing(1, now - Duration::days(2)),
+        ]);
+        assert_eq!(
+            history.scheduled_cards(&deck(5), &settings, now, |_| true),
+            vec![1, 2, 3]
+        );
+
+        // Today's reviews and first sightings count against the limits.
+        history.reviews = vec![
+            review("deck", 0, now - Duration::days(3)),
+            review("deck", 0, now - Duration::hours(1)),
+            review("deck", 9, now - Duration::hours(1)),
+        ];
+        assert_eq!(
+            history.scheduled_cards(&deck(5), &settings, now, |_| true),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn due_counts_follow_the_scheduled_cards() {
+        let now = noon();
+        let settings = DeckSettings {
+            new_cards_per_day: 2,
+            ..DeckSettings::default()
+        };
+        let history = history(vec![
+            learning(0, now - Duration::minutes(5)),
+            reviewing(1, now - Duration::days(1)),
+            reviewing(2, now + Duration::days(1)),
+        ]);
+
+        let counts = history.due_counts(&deck(6), &settings, now);
+        assert_eq!((counts.due, counts.new), (2, 2));
+        assert_eq!(counts.total(), 4);
+    }
+
+    #[test]
+    fn due_counts_estimate_decks_without_cards() {
+        let now = noon();
+        let settings = DeckSettings {
+            new_cards_per_day: 3,
+            reviews_per_day: 1,
+            ..DeckSettings::default()
+        };
+        let mut history = history(vec![
+            learning(0, now - Duration::minutes(5)),
+            reviewing(1, now - Duration::days(1)),
+            reviewing(2, now - Duration::days(2)),
+            reviewing(3, now + Duration::days(1)),
+        ]);
+        history.set_suspended("deck", "card4", true);
+        let mut deck = deck(10);
+        deck.cards.clear();
+
+        // Learning cards are not held back by the review limit; every
+        // card without a state counts as new.
+        let counts = history.due_counts(&deck, &settings, now);
+        assert_eq!((counts.due, counts.new), (2, 3));
+    }
+
+    #[test]
+    fn recorded_rounds_reschedule_their_cards() {
+        let deck = deck(1);
+        let mut history = History::default();
+        let answer = AnsweredCard {
+            card_id: "card0".to_owned(),
+            correct: true,
+            response_ms: None,
+            rating: Rating::Good,
+        };
+        history.record_round(&deck, &[answer], &Sm2, &DeckSettings::default());
+
+        assert_eq!(history.reviews.len(), 1);
+        assert_eq!(history.rounds.len(), 1);
+        assert_eq!(history.card_states["card0"].learning_step, Some(1));
+    }
+
+    /// The instant of a local wall clock time.
+    fn local(day: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+        day.and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn reviewed_on(days: &[NaiveDate]) -> History {
+        History {
+            reviews: days
+                .iter()
+                .map(|day| review("deck", 0, local(*day, 12, 0)))
+                .collect(),
+            ..History::default()
+        }
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn reviews_are_counted_by_local_day() {
+        let history = History {
+            reviews: vec![
+                review("deck", 0, local(day(4), 0, 30)),
+                review("deck", 1, local(day(4), 23, 30)),
+                review("deck", 2, local(day(5), 0, 10)),
+            ],
+            ..History::default()
+        };
+
+        let days = history.reviews_per_day();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[&day(4)], 2);
+        assert_eq!(days[&day(5)], 1);
+    }
+
+    #[test]
+    fn streaks_run_over_consecutive_days() {
+        let history = reviewed_on(&[day(1), day(2), day(3), day(6), day(7), day(7)]);
+
+        let streaks = history.streaks(day(7));
+        assert_eq!((streaks.current, streaks.longest), (2, 3));
+        assert_eq!(History::default().streaks(day(7)).longest, 0);
+    }
+
+    #[test]
+    fn the_current_streak_holds_while_today_is_open() {
+        let history = reviewed_on(&[day(5), day(6)]);
+
+        // Not studied yet today, the run up to yesterday still counts.
+        assert_eq!(history.streaks(day(7)).current, 2);
+        // A whole day without reviews ends it, but not the longest run.
+        let streaks = history.streaks(day(8));
+        assert_eq!((streaks.current, streaks.longest), (0, 2));
+    }
+}
@@ -1,9 +1,37 @@
+mod cache;
+mod charts;
 mod client;
+mod folders;
+mod fsrs;
+mod history;
+mod markdown;
+mod math;
+mod outbox;
+mod preferences;
+mod scheduler;
+mod simulation;
+mod storage;
 mod styling;
+mod tag_filter;
+use crate::cache::DeckCache;
+use crate::charts::{BarChart, Heatmap, LineChart};
 use crate::client::*;
+use crate::folders::Folder;
+use crate::fsrs::{Fit, Fsrs, MIN_FIT_REVIEWS};
+use crate::history::{AnsweredCard, DueCounts, ExamAttempt, History, Review, FORECAST_DAYS};
+use crate::markdown::{Block, MathMode, Span};
+use crate::math::Formula;
+use crate::outbox::{Conflict, Mutation, Outbox, Outcome, Resolution};
+use crate::preferences::{
+    DeckGrouping, DeckSettings, DeckSort, ExamLimit, ExamSettings, LeechAction, Preferences,
+};
+use crate::scheduler::{CardState, Rating, SchedulerKind};
+use crate::simulation::{Simulation, SIMULATION_DAYS};
+use crate::tag_filter::TagFilter;
+use chrono::Datelike;
 use iced::widget::{
-    button, column, container, horizontal_space, progress_bar, radio, row, scrollable, text,
-    text_input,
+    button, canvas::Canvas, checkbox, column, container, horizontal_space, pick_list, progress_bar,
+    radio, row, scrollable, text, text_input,
 };
 use iced::{
     alignment::Horizontal,
@@ -12,11 +40,18 @@ use iced::{
     time::{Duration, Instant},
     Alignment, Application, Command, Element, Length, Padding, Settings, Subscription, Theme,
 };
-use iced_aw::native::Modal;
-use serde::{Deserialize, Serialize};
+use iced_aw::native::{Modal, Wrap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
 
-const ANSWER_DELAY: Duration = Duration::new(1, 0);
 const RESULTS_DELAY: Duration = Duration::new(2, 0);
+const EXAM_TICK: Duration = Duration::from_millis(250);
+const NOTIFICATION_DELAY: Duration = Duration::new(4, 0);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::new(10, 0);
+/// How often the deck list recounts due cards, as learning steps come due.
+const DUE_COUNTS_INTERVAL: Duration = Duration::new(60, 0);
+const RECENTLY_STUDIED_DAYS: i64 = 7;
+const LEECH_TAG: &str = "leech";
 
 pub fn main() -> iced::Result {
     Shisho::run(Settings::default())
@@ -44,6 +79,57 @@ struct Shisho {
     pending_operation: PendingOperation,
     import_file_path: String,
     show_import_file_dialog: bool,
+    history: History,
+    profile: Option<Profile>,
+    profile_display_name: String,
+    profile_bio: String,
+    notifications: Vec<Notification>,
+    next_notification_id: u64,
+    retry_policy: RetryPolicy,
+    offline: bool,
+    /// Due and new cards by deck id, counted when the decks or the history
+    /// change rather than on every render.
+    due_counts: HashMap<String, DueCounts>,
+    deck_cache: DeckCache,
+    outbox: Outbox,
+    conflict: Option<Conflict>,
+    uploading_progress: bool,
+    deck_search: String,
+    deck_filters: DeckFilters,
+    preferences: Preferences,
+    round_title: String,
+    round_cards: Vec<Card>,
+    /// Id of the deck each round card comes from.
+    round_decks: Vec<String>,
+    round_return: TargetView,
+    /// How long each round card took to answer, in milliseconds.
+    response_times: Vec<Option<u64>>,
+    card_shown_at: Instant,
+    round_ratings: Vec<Option<Rating>>,
+    /// Whether the round shows only questions and revealed answers.
+    flashcards: bool,
+    /// Set while the round is an exam, and kept for its results.
+    exam: Option<Exam>,
+    exam_seconds_input: String,
+    /// Whether the editor shows card content rendered as Markdown.
+    edit_preview: bool,
+    /// Round left unfinished, offered for resuming from the deck list.
+    paused_round: Option<SavedRound>,
+    /// Folder whose decks are being fetched before studying it.
+    pending_folder_round: Option<Vec<String>>,
+    show_export_file_dialog: bool,
+    export_file_path: String,
+    round_tag_filter: String,
+    public_decks: Vec<Deck>,
+    public_deck_search: String,
+    /// Public deck shown next to the Discover list, with its cards.
+    public_deck_preview: Option<Deck>,
+    daily_goal_input: String,
+    answer_delay_input: String,
+    simulations: Vec<Simulation>,
+    deck_settings_form: DeckSettingsForm,
+    /// Whether FSRS fitting or a simulation is running.
+    scheduling_busy: bool,
 }
 
 #[derive(Debug)]
@@ -56,17 +142,114 @@ enum States {
     Result,
     Create,
     Edit,
+    Profile,
+    Discover,
+    Statistics,
+    Scheduling,
+    Leeches,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum PendingOperation {
     GetDecks,
     GetDeck(String),
-    DeleteDeck(String),
-    CreateDeck(Deck),
+    SyncOutbox,
+    SearchDecks(String),
+    GetPublicDecks(Option<String>),
+    GetPublicDeck(usize),
+    GetProfile,
+    UpdateProfile(String, String),
     None,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+enum NotificationKind {
+    Error,
+    Success,
+}
+
+#[derive(Debug)]
+struct Notification {
+    /// Stays the same while other notifications come and go.
+    id: u64,
+    kind: NotificationKind,
+    message: String,
+    retry: Option<PendingOperation>,
+    shown_at: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DeckFilter {
+    HasDueCards,
+    OwnedByMe,
+    RecentlyStudied,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DeckFilters {
+    has_due_cards: bool,
+    owned_by_me: bool,
+    recently_studied: bool,
+}
+
+/// Which cards of the chosen decks a round holds, and how they are studied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RoundKind {
+    /// Cards due now, within the decks' daily limits.
+    Scheduled,
+    /// Every card, whether due or not.
+    Cram,
+    /// Every card against the clock, with no feedback until submitted.
+    Exam,
+}
+
+#[derive(Debug)]
+struct Exam {
+    settings: ExamSettings,
+    started_at: Instant,
+    card_started_at: Instant,
+    /// Set once the exam is submitted.
+    time_taken: Option<Duration>,
+}
+
+impl Exam {
+    fn new(settings: ExamSettings) -> Self {
+        let now = Instant::now();
+        Exam {
+            settings,
+            started_at: now,
+            card_started_at: now,
+            time_taken: None,
+        }
+    }
+
+    fn limit(&self) -> Duration {
+        Duration::from_secs(self.settings.seconds)
+    }
+
+    /// Time left on the countdown of the whole exam, or of the current card.
+    fn time_left(&self, now: Instant) -> Duration {
+        let started_at = match self.settings.limit {
+            ExamLimit::Overall => self.started_at,
+            ExamLimit::PerCard => self.card_started_at,
+        };
+        self.limit()
+            .saturating_sub(now.saturating_duration_since(started_at))
+    }
+
+    /// Whether earlier cards can be revisited. Per card limits would restart.
+    fn can_go_back(&self) -> bool {
+        self.settings.allow_changes && self.settings.limit == ExamLimit::Overall
+    }
+
+    fn describe_limit(&self) -> String {
+        match self.settings.limit {
+            ExamLimit::Overall => format!("{} overall", format_clock(self.limit())),
+            ExamLimit::PerCard => format!("{} per card", format_clock(self.limit())),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum TargetView {
     Details,
@@ -83,9 +266,10 @@ enum Message {
     GetDecks,
     HandleDecksResponse(Result<Vec<Deck>, Error>),
     HandleDeckResponse(Result<Deck, Error>),
+    HandleDeckRefresh(Result<Deck, Error>),
     SendCreateDeckRequest,
-    HandleCreateDeckResponse(Result<Deck, Error>),
-    HandleDeleteDeckResponse(Result<reqwest::StatusCode, Error>),
+    HandleOutboxReplay(Result<Outcome, Error>),
+    ResolveConflict(Resolution),
     UsernameChanged(String),
     PasswordChanged(String),
     SignupUsernameChanged(String),
@@ -94,8 +278,32 @@ enum Message {
     SignupNickChanged(String),
     SelectDeck(usize),
     StartRound,
+    StartCramRound,
+    StartExam,
+    ExamLimitChanged(ExamLimit),
+    ExamSecondsChanged(String),
+    ExamAnswerChangesToggled(bool),
+    PreviousExamCard,
+    NextExamCard,
+    SubmitExam,
+    PauseRound,
+    UndoAnswer,
+    ResumeRound,
+    DiscardPausedRound,
+    RoundTagFilterChanged(String),
+    StartFilteredRound,
+    StudyFolder(Vec<String>),
+    HandleFolderDeckResponse(Result<Deck, Error>),
     CancelRound(TargetView),
     Answer(usize),
+    ShowAnswer,
+    /// Enter or Space: reveals a flashcard, or moves on once rated.
+    Continue,
+    AnswerDelayChanged(String),
+    ManualAdvanceToggled(bool),
+    ScoreAnimationToggled(bool),
+    Rate(Rating),
+    FlashcardModeToggled(bool),
     Tick(Instant),
     EditDeck,
     NewDeck,
@@ -107,6 +315,9 @@ enum Message {
     EditDeckTitleChanged(String),
     EditDeckDescriptionChanged(String),
     CardTitleChanged(usize, String),
+    CardTagsChanged(usize, String),
+    CardExplanationChanged(usize, String),
+    EditPreviewToggled(bool),
     AnswerTextChanged((usize, usize), String),
     AnswerIsCorrectChanged((usize, usize), bool),
     FocusNext,
@@ -115,7 +326,61 @@ enum Message {
     ImportFilePathChanged(String),
     ImportFile,
     ShowDialog,
+    ShowExportDialog,
+    HideExportDialog,
+    ExportFilePathChanged(String),
+    ExportDeck,
+    ShowStatistics,
     HideDialog,
+    ShowProfile,
+    HandleProfileResponse(Result<Profile, Error>),
+    ProfileDisplayNameChanged(String),
+    ProfileBioChanged(String),
+    SendUpdateProfile,
+    HandleUpdateProfileResponse(Result<Profile, Error>),
+    RetryNotification(u64),
+    DismissNotification(u64),
+    ExpireNotifications(Instant),
+    RefreshDueCounts,
+    CheckHealth,
+    HandleHealthCheck(bool),
+    HandleProgressDownload(Result<Progress, Error>),
+    HandleProgressUpload(Result<(Vec<Review>, Vec<CardState>), Error>),
+    HandleAccountProfile(Result<Profile, Error>),
+    DeckSearchChanged(String),
+    SearchDecks,
+    HandleSearchResponse(Result<Vec<Deck>, Error>),
+    ToggleDeckFilter(DeckFilter, bool),
+    DeckSortChanged(DeckSort),
+    DeckGroupingChanged(DeckGrouping),
+    ToggleDeckGroup(String),
+    EditDeckFolderChanged(String),
+    EditDeckTagsChanged(String),
+    EditDeckVisibilityChanged(bool),
+    ShowDiscover,
+    PublicDeckSearchChanged(String),
+    SearchPublicDecks,
+    HandlePublicDecksResponse(Result<Vec<Deck>, Error>),
+    PreviewPublicDeck(usize),
+    HandlePublicDeckPreview(Result<Deck, Error>),
+    ClosePublicDeckPreview,
+    ClonePublicDeck,
+    DailyGoalChanged(String),
+    DeckSchedulerChanged(SchedulerKind),
+    NewCardsPerDayChanged(String),
+    ReviewsPerDayChanged(String),
+    LearningStepsChanged(String),
+    LeechThresholdChanged(String),
+    LeechActionChanged(LeechAction),
+    ShowLeeches,
+    ToggleSuspended(String),
+    TagLeeches,
+    ShowScheduling,
+    FitFsrs,
+    HandleFsrsFit(Option<Fit>),
+    ResetFsrs,
+    SimulateSchedulers,
+    HandleSimulations(Vec<Simulation>),
     None(usize),
 }
 
@@ -157,6 +422,46 @@ impl Application for Shisho {
                 pending_operation: PendingOperation::None,
                 import_file_path: "".to_owned(),
                 show_import_file_dialog: false,
+                history: History::default(),
+                profile: None,
+                profile_display_name: "".to_owned(),
+                profile_bio: "".to_owned(),
+                notifications: Vec::new(),
+                next_notification_id: 0,
+                retry_policy: RetryPolicy::default(),
+                offline: false,
+                due_counts: HashMap::new(),
+                deck_cache: DeckCache::default(),
+                outbox: Outbox::default(),
+                conflict: None,
+                uploading_progress: false,
+                deck_search: String::new(),
+                deck_filters: DeckFilters::default(),
+                preferences: Preferences::default(),
+                round_title: String::new(),
+                round_cards: Vec::new(),
+                round_decks: Vec::new(),
+                round_return: TargetView::Details,
+                response_times: Vec::new(),
+                card_shown_at: Instant::now(),
+                round_ratings: Vec::new(),
+                flashcards: false,
+                exam: None,
+                exam_seconds_input: String::new(),
+                edit_preview: false,
+                paused_round: None,
+                pending_folder_round: None,
+                show_export_file_dialog: false,
+                export_file_path: String::new(),
+                round_tag_filter: String::new(),
+                public_decks: Vec::new(),
+                public_deck_search: String::new(),
+                public_deck_preview: None,
+                daily_goal_input: String::new(),
+                answer_delay_input: String::new(),
+                simulations: Vec::new(),
+                deck_settings_form: DeckSettingsForm::default(),
+                scheduling_busy: false,
             },
             Command::none(),
         )
@@ -168,11 +473,32 @@ impl Application for Shisho {
 
     fn subscription(&self) -> Subscription<Message> {
         let mut subscriptions = Vec::new();
-        let time_subscription = match self.answered {
-            false => Subscription::none(),
-            true => iced::time::every(Duration::from_millis(10)).map(Message::Tick),
+        let exam_running = self.exam.is_some() && matches!(self.state, States::Round);
+        // The exam countdown shows whole seconds, so it needs far fewer
+        // ticks than the feedback animation.
+        let time_subscription = match (exam_running, self.answered) {
+            (true, _) => iced::time::every(EXAM_TICK).map(Message::Tick),
+            (false, true) => iced::time::every(Duration::from_millis(10)).map(Message::Tick),
+            (false, false) => Subscription::none(),
         };
         subscriptions.push(time_subscription);
+        if self
+            .notifications
+            .iter()
+            .any(|n| n.kind == NotificationKind::Success)
+        {
+            subscriptions.push(
+                iced::time::every(Duration::from_millis(500)).map(Message::ExpireNotifications),
+            );
+        }
+        if self.offline {
+            subscriptions
+                .push(iced::time::every(HEALTH_CHECK_INTERVAL).map(|_| Message::CheckHealth));
+        }
+        if let States::Loaded = self.state {
+            subscriptions
+                .push(iced::time::every(DUE_COUNTS_INTERVAL).map(|_| Message::RefreshDueCounts));
+        }
         let event_subscription = iced::subscription::events().map(filter_event);
         subscriptions.push(event_subscription);
 
@@ -182,43 +508,119 @@ impl Application for Shisho {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::GetDecks => {
-                Command::perform(get_decks(self.token.clone()), Message::HandleDecksResponse)
+                self.pending_operation = PendingOperation::GetDecks;
+
+                Command::perform(
+                    get_decks(self.token.clone(), self.retry_policy),
+                    Message::HandleDecksResponse,
+                )
             }
-            Message::HandleDecksResponse(result) => {
+            Message::HandleDecksResponse(result) => match result {
+                Ok(mut decks) => {
+                    let selected_id = self
+                        .already_selected
+                        .then(|| self.decks[self.selected_deck].id.clone());
+                    self.outbox.apply_to(&mut decks);
+                    self.deck_cache.store_list(&decks);
+                    let (decks, fully_fetched) = self.deck_cache.decks();
+                    self.decks = decks;
+                    self.fully_fetched = fully_fetched;
+                    self.pending_operation = PendingOperation::None;
+                    self.save_deck_cache();
+
+                    // Refreshes keep the selected deck, wherever it moved in the list.
+                    match selected_id.and_then(|id| self.decks.iter().position(|d| d.id == id)) {
+                        Some(index) => self.selected_deck = index,
+                        None => {
+                            self.already_selected = false;
+                            if let States::Details
+                            | States::Statistics
+                            | States::Scheduling
+                            | States::Leeches = self.state
+                            {
+                                self.state = States::Loaded;
+                            }
+                        }
+                    }
+                    self.leave_loading_screen();
+
+                    let mut commands: Vec<Command<Message>> = self
+                        .deck_cache
+                        .full
+                        .keys()
+                        .filter(|id| !self.outbox.touches(id))
+                        .map(|id| {
+                            Command::perform(
+                                get_deck(self.token.clone(), id.clone(), self.retry_policy),
+                                Message::HandleDeckRefresh,
+                            )
+                        })
+                        .collect();
+                    commands.push(self.sync_outbox());
+                    Command::batch(commands)
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    self.notify_error(&err, Some(PendingOperation::GetDecks));
+                    if self.decks.is_empty() {
+                        self.load_cached_decks();
+                    }
+                    self.leave_loading_screen();
+
+                    Command::none()
+                }
+            },
+            Message::HandleDeckRefresh(result) => {
                 match result {
-                    Ok(decks) => {
-                        self.fully_fetched = vec![false; decks.len()];
-                        self.decks = decks;
-                        self.pending_operation = PendingOperation::None;
+                    Ok(deck) => {
+                        if self.outbox.touches(&deck.id) {
+                            return Command::none();
+                        }
+                        self.deck_cache.store_deck(&deck);
+                        if let Some(index) = self.decks.iter().position(|d| d.id == deck.id) {
+                            self.decks[index] = deck;
+                            self.fully_fetched[index] = true;
+                        }
+                        self.save_deck_cache();
                     }
                     Err(err) => {
-                        print!("{:#?}", err);
-                        self.remote_error = Some(err);
+                        eprintln!("Background refresh failed: {}", err);
+                        self.track_connectivity(&err);
                     }
                 }
-                self.state = States::Loaded;
 
                 Command::none()
             }
             Message::HandleDeckResponse(result) => match result {
                 Ok(deck) => {
-                    self.fully_fetched[self.selected_deck] = true;
-                    self.decks[self.selected_deck].cards = deck.cards;
+                    self.deck_cache.store_deck(&deck);
                     self.pending_operation = PendingOperation::None;
-                    self.state = States::Details;
+                    // The decks may have been refreshed or reordered while
+                    // this one was being fetched.
+                    if let Some(index) = self.decks.iter().position(|d| d.id == deck.id) {
+                        self.fully_fetched[index] = true;
+                        self.decks[index].cards = deck.cards;
+                        if index == self.selected_deck {
+                            self.state = States::Details;
+                        }
+                    }
+                    self.save_deck_cache();
 
                     Command::none()
                 }
                 Err(err) => {
-                    print!("{:#?}", err);
+                    eprintln!("{}", err);
                     match err {
                         Error::AuthError => {
                             let login = self.login.clone();
 
-                            Command::perform(log_in(login), Message::HandleAuthResponse)
+                            Command::perform(
+                                log_in(login, self.retry_policy),
+                                Message::HandleAuthResponse,
+                            )
                         }
                         _ => {
-                            self.remote_error = Some(err);
+                            self.notify_error(&err, Some(self.pending_operation.clone()));
 
                             Command::none()
                         }
@@ -226,91 +628,97 @@ impl Application for Shisho {
                 }
             },
             Message::SendCreateDeckRequest => {
-                let deck = deck_from_edit_deck(&self.edit_deck);
-
-                Command::perform(
-                    create_deck(self.token.clone(), deck),
-                    Message::HandleCreateDeckResponse,
-                )
-            }
-            Message::HandleCreateDeckResponse(result) => match result {
-                Ok(deck) => match self.state {
-                    States::Create => {
-                        self.decks.push(deck);
-                        self.fully_fetched.push(true);
-                        self.state = States::Loaded;
-
-                        let index = self.decks.len() - 1;
-                        self.show_import_file_dialog = false;
-                        self.select_deck(index)
+                let mut deck = deck_from_edit_deck(&self.edit_deck);
+                for card in deck.cards.iter_mut() {
+                    if card.id.is_empty() {
+                        card.id = outbox::local_id();
                     }
+                }
+
+                let index = match self.state {
                     States::Edit => {
-                        let id = self.decks[self.selected_deck].id.clone();
+                        let base = self.decks[self.selected_deck].clone();
+                        deck.id = base.id.clone();
+                        deck.owner_id = base.owner_id.clone();
+                        deck.created_at = base.created_at;
+                        self.outbox.push(Mutation::Update {
+                            base,
+                            deck: deck.clone(),
+                        });
+                        self.deck_cache.store_deck(&deck);
                         self.decks[self.selected_deck] = deck;
-                        self.state = States::Details;
-                        self.show_import_file_dialog = false;
-                        Command::perform(
-                            delete_deck(self.token.clone(), id),
-                            Message::HandleDeleteDeckResponse,
-                        )
+                        self.selected_deck
                     }
-                    _ => Command::none(),
-                },
-                Err(err) => {
-                    print!("{:#?}", err);
-                    match err {
-                        Error::AuthError => {
-                            let deck = deck_from_edit_deck(&self.edit_deck);
-                            self.pending_operation = PendingOperation::CreateDeck(deck);
-                            let login = self.login.clone();
+                    _ => self.add_local_deck(deck),
+                };
+                self.show_import_file_dialog = false;
+                self.save_outbox();
+                self.save_deck_cache();
+                if self.offline {
+                    self.notify_success("Saved offline, it will be uploaded once back online");
+                }
 
-                            Command::perform(log_in(login), Message::HandleAuthResponse)
-                        }
-                        _ => {
-                            self.remote_error = Some(err);
+                Command::batch([self.select_deck(index), self.sync_outbox()])
+            }
+            Message::HandleOutboxReplay(result) => {
+                self.outbox.in_flight = false;
+                match result {
+                    Ok(Outcome::Applied { mutation, created }) => {
+                        self.outbox.pop_front();
+                        self.apply_synced(&mutation, created);
+                        self.save_outbox();
 
-                            Command::none()
-                        }
+                        Command::batch([self.sync_outbox(), self.upload_progress()])
                     }
-                }
-            },
-            Message::HandleDeleteDeckResponse(result) => match result {
-                Ok(_) => {
-                    self.pending_operation = PendingOperation::None;
-                    Command::none()
-                }
-                Err(err) => {
-                    print!("{:#?}", err);
-                    match err {
-                        Error::AuthError => {
-                            let login = self.login.clone();
+                    Ok(Outcome::Conflict(conflict)) => {
+                        self.conflict = Some(conflict);
 
-                            Command::perform(log_in(login), Message::HandleAuthResponse)
-                        }
-                        _ => {
-                            self.remote_error = Some(err);
+                        Command::none()
+                    }
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        match err {
+                            Error::AuthError => {
+                                self.pending_operation = PendingOperation::SyncOutbox;
+                                let login = self.login.clone();
+
+                                Command::perform(
+                                    log_in(login, self.retry_policy),
+                                    Message::HandleAuthResponse,
+                                )
+                            }
+                            _ => {
+                                self.notify_error(&err, Some(PendingOperation::SyncOutbox));
 
-                            Command::none()
+                                Command::none()
+                            }
                         }
                     }
                 }
+            }
+            Message::ResolveConflict(resolution) => match self.conflict.take() {
+                Some(conflict) => self.resolve_conflict(conflict, resolution),
+                None => Command::none(),
             },
             Message::DeleteDeck => {
-                let id = self.decks[self.selected_deck].id.clone();
-                self.decks.remove(self.selected_deck);
+                let base = self.decks.remove(self.selected_deck);
+                self.fully_fetched.remove(self.selected_deck);
+                self.deck_cache.remove(&base.id);
+                self.outbox.push(Mutation::Delete { base });
+                self.save_outbox();
+                self.save_deck_cache();
                 self.already_selected = false;
-                self.pending_operation = PendingOperation::DeleteDeck(id.clone());
                 self.state = States::Loaded;
 
-                Command::perform(
-                    delete_deck(self.token.clone(), id),
-                    Message::HandleDeleteDeckResponse,
-                )
+                self.sync_outbox()
             }
             Message::SendLogIn => {
                 let login = self.login.clone();
 
-                Command::perform(log_in(login), Message::HandleAuthResponse)
+                Command::perform(
+                    log_in(login, self.retry_policy),
+                    Message::HandleAuthResponse,
+                )
             }
             Message::SignUp => {
                 self.signup.username = self.login.username.clone();
@@ -322,35 +730,43 @@ impl Application for Shisho {
             Message::HandleAuthResponse(result) => match result {
                 Ok(t) => {
                     self.token = t.token.clone();
+                    self.remote_error = None;
                     if self.first_login == true {
                         self.first_login = false;
+                        self.load_local_data();
                         self.pending_operation = PendingOperation::GetDecks;
-                        Command::perform(get_decks(t.token), Message::HandleDecksResponse)
-                    } else {
-                        match &self.pending_operation {
-                            PendingOperation::GetDecks => {
-                                Command::perform(get_decks(t.token), Message::HandleDecksResponse)
-                            }
-                            PendingOperation::GetDeck(id) => Command::perform(
-                                get_deck(self.token.clone(), id.to_owned()),
-                                Message::HandleDeckResponse,
+                        Command::batch([
+                            Command::perform(
+                                get_decks(t.token.clone(), self.retry_policy),
+                                Message::HandleDecksResponse,
                             ),
-                            PendingOperation::DeleteDeck(id) => Command::perform(
-                                delete_deck(self.token.clone(), id.to_owned()),
-                                Message::HandleDeleteDeckResponse,
+                            Command::perform(
+                                get_profile(t.token, self.retry_policy),
+                                Message::HandleAccountProfile,
                             ),
-                            PendingOperation::CreateDeck(deck) => Command::perform(
-                                create_deck(self.token.clone(), deck.clone()),
-                                Message::HandleCreateDeckResponse,
-                            ),
-                            PendingOperation::None => Command::none(),
-                        }
+                            self.download_progress(),
+                        ])
+                    } else {
+                        self.perform(self.pending_operation.clone())
                     }
                 }
                 Err(err) => {
-                    print!("{:#?}", err);
+                    eprintln!("{}", err);
+                    self.track_connectivity(&err);
+                    match self.state {
+                        States::Welcome if self.offline => {
+                            self.load_local_data();
+                            if self.load_cached_decks() {
+                                self.state = States::Loaded;
+                                self.notify_error(&err, None);
+                            } else {
+                                self.remote_error = Some(err);
+                            }
+                        }
+                        States::Welcome | States::Signup => self.remote_error = Some(err),
+                        _ => self.notify_error(&err, Some(self.pending_operation.clone())),
+                    }
                     self.pending_operation = PendingOperation::None;
-                    self.remote_error = Some(err);
 
                     Command::none()
                 }
@@ -395,11 +811,43 @@ impl Application for Shisho {
 
                 Command::none()
             }
+            Message::EditDeckFolderChanged(new_folder) => {
+                self.edit_deck.folder = new_folder;
+
+                Command::none()
+            }
+            Message::EditDeckVisibilityChanged(is_public) => {
+                self.edit_deck.is_public = is_public;
+
+                Command::none()
+            }
+            Message::EditDeckTagsChanged(new_tags) => {
+                // Kept untrimmed so the field shows exactly what was typed.
+                self.edit_deck.tags = new_tags.split(',').map(|tag| tag.to_owned()).collect();
+
+                Command::none()
+            }
             Message::CardTitleChanged(index, new_title) => {
                 self.edit_deck.cards[index].title = new_title;
 
                 Command::none()
             }
+            Message::CardExplanationChanged(index, explanation) => {
+                self.edit_deck.cards[index].explanation = explanation;
+
+                Command::none()
+            }
+            Message::EditPreviewToggled(enabled) => {
+                self.edit_preview = enabled;
+
+                Command::none()
+            }
+            Message::CardTagsChanged(index, new_tags) => {
+                self.edit_deck.cards[index].tags =
+                    new_tags.split(',').map(|tag| tag.to_owned()).collect();
+
+                Command::none()
+            }
             Message::AnswerTextChanged((card_index, answer_index), new_text) => {
                 self.edit_deck.cards[card_index].answers[answer_index].text = new_text;
 
@@ -412,8 +860,13 @@ impl Application for Shisho {
             }
             Message::SendSignUp => {
                 let signup = self.signup.clone();
+                self.login.username = signup.username.clone();
+                self.login.password = signup.password.clone();
 
-                Command::perform(sign_up(signup), Message::HandleAuthResponse)
+                Command::perform(
+                    sign_up(signup, self.retry_policy),
+                    Message::HandleAuthResponse,
+                )
             }
             Message::SelectDeck(index) => self.select_deck(index),
             Message::ToLoginFromSignUp => {
@@ -429,27 +882,204 @@ impl Application for Shisho {
                 Command::none()
             }
             Message::StartRound => {
-                if self.decks[self.selected_deck].cards.len() > 0 {
-                    self.state = States::Round;
-                    self.selected_answers = Vec::new();
-                    for card in self.decks[self.selected_deck].cards.iter() {
-                        self.selected_answers.push(vec![false; card.answers.len()])
+                let title = self.decks[self.selected_deck].title.clone();
+                self.start_round(
+                    &[self.selected_deck],
+                    title,
+                    TargetView::Details,
+                    None,
+                    RoundKind::Scheduled,
+                );
+
+                Command::none()
+            }
+            Message::StartCramRound => {
+                let title = format!("{} (all cards)", self.decks[self.selected_deck].title);
+                self.start_round(
+                    &[self.selected_deck],
+                    title,
+                    TargetView::Details,
+                    None,
+                    RoundKind::Cram,
+                );
+
+                Command::none()
+            }
+            Message::StartExam => {
+                let title = format!("{} (exam)", self.decks[self.selected_deck].title);
+                self.start_round(
+                    &[self.selected_deck],
+                    title,
+                    TargetView::Details,
+                    None,
+                    RoundKind::Exam,
+                );
+
+                Command::none()
+            }
+            Message::ExamLimitChanged(limit) => {
+                self.preferences.exam.limit = limit;
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::ExamSecondsChanged(seconds) => {
+                if let Ok(parsed) = seconds.trim().parse::<u64>() {
+                    if parsed > 0 {
+                        self.preferences.exam.seconds = parsed;
+                        self.save_preferences();
                     }
                 }
+                self.exam_seconds_input = seconds;
 
                 Command::none()
             }
-            Message::CancelRound(target) => {
-                self.selected_card = 0;
-                self.check = false;
-                self.duration = Duration::ZERO;
-                self.last_tick = Instant::now();
-                self.answered = false;
-                let state = match target {
-                    TargetView::Details => States::Details,
-                    TargetView::Welcome => States::Loaded,
+            Message::ExamAnswerChangesToggled(enabled) => {
+                self.preferences.exam.allow_changes = enabled;
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::PreviousExamCard => {
+                if let (States::Round, Some(exam)) = (&self.state, &self.exam) {
+                    if exam.can_go_back() && self.selected_card > 0 {
+                        self.selected_card -= 1;
+                    }
+                }
+
+                Command::none()
+            }
+            Message::NextExamCard => {
+                if let (States::Round, Some(_)) = (&self.state, &self.exam) {
+                    if self.selected_card + 1 < self.round_cards.len() {
+                        self.advance_exam();
+                    }
+                }
+
+                Command::none()
+            }
+            Message::SubmitExam => {
+                if let States::Round = self.state {
+                    self.submit_exam();
+                }
+
+                Command::none()
+            }
+            Message::RoundTagFilterChanged(filter) => {
+                self.round_tag_filter = filter;
+
+                Command::none()
+            }
+            Message::StartFilteredRound => {
+                let filter = match TagFilter::parse(&self.round_tag_filter) {
+                    Ok(filter) => filter,
+                    Err(_) => return Command::none(),
+                };
+                let title = format!(
+                    "{} ({})",
+                    self.decks[self.selected_deck].title,
+                    self.round_tag_filter.trim()
+                );
+                self.start_round(
+                    &[self.selected_deck],
+                    title,
+                    TargetView::Details,
+                    Some(&filter),
+                    RoundKind::Scheduled,
+                );
+
+                Command::none()
+            }
+            Message::StudyFolder(path) => {
+                let missing: Vec<String> = self
+                    .decks_in_folder(&path)
+                    .into_iter()
+                    .filter(|index| !self.fully_fetched[*index])
+                    .map(|index| self.decks[index].id.clone())
+                    .collect();
+
+                if missing.is_empty() {
+                    self.start_folder_round(&path);
+                    return Command::none();
+                }
+
+                self.pending_folder_round = Some(path);
+                Command::batch(missing.into_iter().map(|id| {
+                    Command::perform(
+                        get_deck(self.token.clone(), id, self.retry_policy),
+                        Message::HandleFolderDeckResponse,
+                    )
+                }))
+            }
+            Message::HandleFolderDeckResponse(result) => {
+                let path = match &self.pending_folder_round {
+                    Some(path) => path.clone(),
+                    None => return Command::none(),
                 };
-                self.state = state;
+
+                match result {
+                    Ok(deck) => {
+                        self.deck_cache.store_deck(&deck);
+                        if let Some(index) = self.decks.iter().position(|d| d.id == deck.id) {
+                            self.decks[index] = deck;
+                            self.fully_fetched[index] = true;
+                        }
+                        self.save_deck_cache();
+
+                        let fetched = self
+                            .decks_in_folder(&path)
+                            .into_iter()
+                            .all(|index| self.fully_fetched[index]);
+                        if fetched {
+                            self.pending_folder_round = None;
+                            self.start_folder_round(&path);
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        self.pending_folder_round = None;
+                        self.notify_error(&err, None);
+                    }
+                }
+
+                Command::none()
+            }
+            Message::CancelRound(target) => {
+                if let (States::Round, None) = (&self.state, &self.exam) {
+                    storage::remove(&self.login.username, storage::ROUND_FILE);
+                }
+                self.leave_round(target);
+
+                Command::none()
+            }
+            Message::PauseRound => {
+                if let (States::Round, None) = (&self.state, &self.exam) {
+                    let round = self.saved_round();
+                    storage::save(&self.login.username, storage::ROUND_FILE, &round);
+                    self.paused_round = Some(round);
+                    self.leave_round(self.round_return.clone());
+                    self.notify_success("Round paused, resume it from the deck list");
+                }
+
+                Command::none()
+            }
+            Message::UndoAnswer => {
+                if let (States::Round, None) = (&self.state, &self.exam) {
+                    self.undo_answer();
+                }
+
+                Command::none()
+            }
+            Message::ResumeRound => {
+                if let Some(round) = self.paused_round.take() {
+                    self.resume_round(round);
+                }
+
+                Command::none()
+            }
+            Message::DiscardPausedRound => {
+                self.paused_round = None;
+                storage::remove(&self.login.username, storage::ROUND_FILE);
 
                 Command::none()
             }
@@ -458,31 +1088,86 @@ impl Application for Shisho {
 
                 Command::none()
             }
+            Message::ShowAnswer => {
+                if let States::Round = self.state {
+                    if self.flashcards {
+                        self.reveal();
+                    }
+                }
+
+                Command::none()
+            }
+            Message::Continue => {
+                if let States::Round = self.state {
+                    if self.exam.is_some() {
+                        return self.update(Message::NextExamCard);
+                    } else if !self.answered && self.flashcards {
+                        self.reveal();
+                    } else if self.answered && self.round_ratings[self.selected_card].is_some() {
+                        return self.next_card();
+                    }
+                }
+
+                Command::none()
+            }
+            Message::AnswerDelayChanged(delay) => {
+                if let Some(delay_ms) = preferences::parse_answer_delay(&delay) {
+                    self.preferences.answer_delay_ms = delay_ms;
+                    self.save_preferences();
+                }
+                self.answer_delay_input = delay;
+
+                Command::none()
+            }
+            Message::ManualAdvanceToggled(enabled) => {
+                self.preferences.manual_advance = enabled;
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::ScoreAnimationToggled(enabled) => {
+                self.preferences.animate_score = enabled;
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::Rate(rating) => {
+                self.rate(rating);
+
+                Command::none()
+            }
+            Message::FlashcardModeToggled(enabled) => {
+                self.preferences.flashcard_mode = enabled;
+                self.save_preferences();
+
+                Command::none()
+            }
             Message::Tick(now) => {
                 match self.state {
+                    States::Round if self.exam.is_some() => {
+                        let expired = self
+                            .exam
+                            .as_ref()
+                            .filter(|exam| exam.time_left(now).is_zero())
+                            .map(|exam| exam.settings.limit);
+                        match expired {
+                            Some(ExamLimit::Overall) => self.submit_exam(),
+                            Some(ExamLimit::PerCard) => self.advance_exam(),
+                            None => {}
+                        }
+                    }
                     States::Round => match self.answered {
                         false => {}
                         true => {
                             self.duration += now - self.last_tick;
                             self.last_tick = now;
 
-                            if self.duration.gt(&ANSWER_DELAY) {
-                                if self.selected_card
-                                    == self.decks[self.selected_deck].cards.len() - 1
-                                {
-                                    self.state = States::Result;
-                                    self.duration = Duration::ZERO;
-                                    self.last_tick = Instant::now();
-                                    self.selected_card = 0;
-                                    self.score = calculate_score(
-                                        &self.decks[self.selected_deck].cards,
-                                        &self.selected_answers,
-                                    )
-                                } else {
-                                    self.selected_card += 1;
-                                    self.check = false;
-                                    self.answered = false;
-                                }
+                            let rated = self.round_ratings[self.selected_card].is_some();
+                            if rated
+                                && !self.preferences.manual_advance
+                                && self.duration.gt(&self.answer_delay())
+                            {
+                                return self.next_card();
                             }
                         }
                     },
@@ -547,7 +1232,12 @@ impl Application for Shisho {
             Message::FocusPrevious => iced::widget::focus_previous(),
             Message::KeyboardAnswer(answer) => {
                 match self.state {
-                    States::Round => self.answer(answer),
+                    States::Round if self.answered => {
+                        if let Some(rating) = Rating::ALL.get(answer) {
+                            self.rate(*rating);
+                        }
+                    }
+                    States::Round if !self.flashcards => self.answer(answer),
                     _ => {}
                 }
 
@@ -559,34 +1249,596 @@ impl Application for Shisho {
 
                 Command::none()
             }
-            Message::ShowDialog => {
-                self.show_import_file_dialog = true;
+            Message::ShowExportDialog => {
+                let file_name = format!("{}.json", self.decks[self.selected_deck].title);
+                self.export_file_path = dirs::home_dir()
+                    .map(|dir| dir.join(&file_name))
+                    .unwrap_or_else(|| file_name.into())
+                    .to_string_lossy()
+                    .into_owned();
+                self.show_export_file_dialog = true;
 
                 Command::none()
             }
-            Message::HideDialog => {
-                self.show_import_file_dialog = false;
+            Message::DailyGoalChanged(goal) => {
+                if let Ok(daily_goal) = goal.trim().parse::<usize>() {
+                    if daily_goal > 0 {
+                        self.preferences.daily_goal = daily_goal;
+                        self.save_preferences();
+                    }
+                }
+                self.daily_goal_input = goal;
 
                 Command::none()
             }
-            Message::ImportFile => {
-                let deck = import_deck_from_file(&self.import_file_path);
-                if deck.is_err() {
-                    dbg!(deck.err());
-                    Command::none()
-                } else {
-                    Command::perform(
-                        create_deck(self.token.clone(), deck_from_edit_deck(&deck.unwrap())),
-                        Message::HandleCreateDeckResponse,
-                    )
+            Message::DeckSchedulerChanged(scheduler) => {
+                self.update_deck_settings(|settings| settings.scheduler = scheduler);
+
+                Command::none()
+            }
+            Message::NewCardsPerDayChanged(limit) => {
+                if let Ok(limit) = limit.trim().parse::<usize>() {
+                    self.update_deck_settings(|settings| settings.new_cards_per_day = limit);
                 }
+                self.deck_settings_form.new_cards_per_day = limit;
+
+                Command::none()
             }
-        }
-    }
+            Message::ReviewsPerDayChanged(limit) => {
+                if let Ok(limit) = limit.trim().parse::<usize>() {
+                    self.update_deck_settings(|settings| settings.reviews_per_day = limit);
+                }
+                self.deck_settings_form.reviews_per_day = limit;
 
-    fn view(&self) -> Element<Message> {
-        let content = match self.state {
-            States::Welcome => self.welcome_view(),
+                Command::none()
+            }
+            Message::LearningStepsChanged(steps) => {
+                if let Some(learning_steps) = preferences::parse_learning_steps(&steps) {
+                    self.update_deck_settings(|settings| settings.learning_steps = learning_steps);
+                }
+                self.deck_settings_form.learning_steps = steps;
+
+                Command::none()
+            }
+            Message::LeechThresholdChanged(threshold) => {
+                if let Ok(threshold) = threshold.trim().parse::<u32>() {
+                    self.update_deck_settings(|settings| settings.leech_threshold = threshold);
+                }
+                self.deck_settings_form.leech_threshold = threshold;
+
+                Command::none()
+            }
+            Message::LeechActionChanged(action) => {
+                self.update_deck_settings(|settings| settings.leech_action = action);
+
+                Command::none()
+            }
+            Message::ShowLeeches => {
+                self.state = States::Leeches;
+
+                Command::none()
+            }
+            Message::ToggleSuspended(card_id) => {
+                let deck_id = self.decks[self.selected_deck].id.clone();
+                let suspended = self.history.is_suspended(&card_id);
+                self.history.set_suspended(&deck_id, &card_id, !suspended);
+                self.save_history();
+
+                self.upload_progress()
+            }
+            Message::TagLeeches => {
+                let deck = &self.decks[self.selected_deck];
+                let settings = self.preferences.deck_settings(&deck.id);
+                let deck_id = deck.id.clone();
+                let card_ids: Vec<String> = self
+                    .history
+                    .leeches(deck, &settings)
+                    .into_iter()
+                    .map(|(card, _)| card.id.clone())
+                    .collect();
+                self.tag_leeches(&deck_id, &card_ids);
+
+                self.sync_outbox()
+            }
+            Message::ShowScheduling => {
+                self.state = States::Scheduling;
+
+                Command::none()
+            }
+            Message::FitFsrs => {
+                self.scheduling_busy = true;
+                let reviews = self.history.reviews.clone();
+
+                // Fitting takes seconds on a long history, too long to hold an
+                // executor thread.
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || Fsrs::fit(&reviews))
+                            .await
+                            .ok()
+                            .flatten()
+                    },
+                    Message::HandleFsrsFit,
+                )
+            }
+            Message::HandleFsrsFit(fit) => {
+                self.scheduling_busy = false;
+                match fit {
+                    Some(fit) => {
+                        self.preferences.fsrs = Some(fit.fsrs);
+                        self.save_preferences();
+                        self.simulations.clear();
+                        self.notify_success(&format!(
+                            "FSRS fitted to {} reviews, log loss {:.3} to {:.3}",
+                            fit.reviews, fit.initial_loss, fit.loss
+                        ));
+                    }
+                    None => self.notify_success(&format!(
+                        "FSRS needs at least {} repeated reviews to fit",
+                        MIN_FIT_REVIEWS
+                    )),
+                }
+
+                Command::none()
+            }
+            Message::ResetFsrs => {
+                self.preferences.fsrs = None;
+                self.save_preferences();
+                self.simulations.clear();
+
+                Command::none()
+            }
+            Message::SimulateSchedulers => {
+                self.scheduling_busy = true;
+                let reviews = self.history.reviews.clone();
+                let model = self.fsrs();
+
+                Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            let now = chrono::Utc::now();
+                            SchedulerKind::ALL
+                                .iter()
+                                .map(|kind| simulation::simulate(&reviews, *kind, &model, now))
+                                .collect::<Vec<Simulation>>()
+                        })
+                        .await
+                        .unwrap_or_default()
+                    },
+                    Message::HandleSimulations,
+                )
+            }
+            Message::HandleSimulations(simulations) => {
+                self.scheduling_busy = false;
+                self.simulations = simulations;
+
+                Command::none()
+            }
+            Message::ShowStatistics => {
+                self.state = States::Statistics;
+
+                Command::none()
+            }
+            Message::HideExportDialog => {
+                self.show_export_file_dialog = false;
+
+                Command::none()
+            }
+            Message::ExportFilePathChanged(path) => {
+                self.export_file_path = path;
+
+                Command::none()
+            }
+            Message::ExportDeck => {
+                let deck: EditDeck = (&self.decks[self.selected_deck]).into();
+                match export_deck_to_file(&self.export_file_path, &deck) {
+                    Ok(()) => {
+                        self.show_export_file_dialog = false;
+                        self.notify_success(&format!("Exported to {}", self.export_file_path));
+                    }
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        self.notify_error(&err, None);
+                    }
+                }
+
+                Command::none()
+            }
+            Message::ShowDialog => {
+                self.show_import_file_dialog = true;
+
+                Command::none()
+            }
+            Message::HideDialog => {
+                self.show_import_file_dialog = false;
+
+                Command::none()
+            }
+            Message::ImportFile => {
+                let deck = import_deck_from_file(&self.import_file_path);
+                if let Err(err) = deck {
+                    eprintln!("{}", err);
+                    self.notify_error(&err, None);
+                    Command::none()
+                } else {
+                    self.edit_deck = deck.unwrap();
+                    self.update(Message::SendCreateDeckRequest)
+                }
+            }
+            Message::ShowDiscover => {
+                self.state = States::Discover;
+                self.public_deck_preview = None;
+
+                self.update(Message::SearchPublicDecks)
+            }
+            Message::PublicDeckSearchChanged(search) => {
+                self.public_deck_search = search;
+
+                Command::none()
+            }
+            Message::SearchPublicDecks => {
+                let search = self.public_deck_search.trim();
+                let search = (!search.is_empty()).then(|| search.to_owned());
+                self.pending_operation = PendingOperation::GetPublicDecks(search.clone());
+
+                Command::perform(
+                    get_public_decks(self.token.clone(), search, self.retry_policy),
+                    Message::HandlePublicDecksResponse,
+                )
+            }
+            Message::HandlePublicDecksResponse(result) => match result {
+                Ok(decks) => {
+                    self.public_decks = decks;
+                    self.pending_operation = PendingOperation::None;
+
+                    Command::none()
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    match err {
+                        Error::AuthError => {
+                            let login = self.login.clone();
+
+                            Command::perform(
+                                log_in(login, self.retry_policy),
+                                Message::HandleAuthResponse,
+                            )
+                        }
+                        _ => {
+                            self.notify_error(&err, Some(self.pending_operation.clone()));
+
+                            Command::none()
+                        }
+                    }
+                }
+            },
+            Message::PreviewPublicDeck(index) => {
+                if index >= self.public_decks.len() {
+                    return Command::none();
+                }
+                self.pending_operation = PendingOperation::GetPublicDeck(index);
+
+                Command::perform(
+                    get_deck(
+                        self.token.clone(),
+                        self.public_decks[index].id.clone(),
+                        self.retry_policy,
+                    ),
+                    Message::HandlePublicDeckPreview,
+                )
+            }
+            Message::HandlePublicDeckPreview(result) => match result {
+                Ok(mut deck) => {
+                    if let Some(listed) = self.public_decks.iter().find(|d| d.id == deck.id) {
+                        deck.author = listed.author.clone();
+                    }
+                    self.public_deck_preview = Some(deck);
+                    self.pending_operation = PendingOperation::None;
+
+                    Command::none()
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    match err {
+                        Error::AuthError => {
+                            let login = self.login.clone();
+
+                            Command::perform(
+                                log_in(login, self.retry_policy),
+                                Message::HandleAuthResponse,
+                            )
+                        }
+                        _ => {
+                            self.notify_error(&err, Some(self.pending_operation.clone()));
+
+                            Command::none()
+                        }
+                    }
+                }
+            },
+            Message::ClosePublicDeckPreview => {
+                self.public_deck_preview = None;
+
+                Command::none()
+            }
+            Message::ClonePublicDeck => {
+                let source = match &self.public_deck_preview {
+                    Some(deck) => deck.clone(),
+                    None => return Command::none(),
+                };
+                let owner_id = match &self.profile {
+                    Some(profile) => profile.id.clone(),
+                    None => {
+                        self.notify_success("Your profile is still loading, try again shortly");
+                        return Command::none();
+                    }
+                };
+
+                // The copy belongs to the user, outside their folders and tags
+                // until they file it. Fresh card ids keep its review history
+                // and scheduling apart from the original's.
+                let mut deck = Deck {
+                    owner_id,
+                    folder: Vec::new(),
+                    tags: Vec::new(),
+                    is_public: false,
+                    author: String::new(),
+                    ..source
+                };
+                for card in deck.cards.iter_mut() {
+                    card.id = outbox::local_id();
+                }
+                self.add_local_deck(deck);
+                self.save_outbox();
+                self.save_deck_cache();
+                self.notify_success(&format!(
+                    "\"{}\" was added to your library",
+                    self.decks[self.decks.len() - 1].title
+                ));
+
+                self.sync_outbox()
+            }
+            Message::ShowProfile => {
+                self.state = States::Profile;
+                self.pending_operation = PendingOperation::GetProfile;
+
+                Command::perform(
+                    get_profile(self.token.clone(), self.retry_policy),
+                    Message::HandleProfileResponse,
+                )
+            }
+            Message::HandleUpdateProfileResponse(result) => {
+                if result.is_ok() {
+                    self.notify_success("Profile saved");
+                }
+
+                self.update(Message::HandleProfileResponse(result))
+            }
+            Message::HandleProfileResponse(result) => match result {
+                Ok(profile) => {
+                    self.profile_display_name = profile.display_name.clone();
+                    self.profile_bio = profile.bio.clone();
+                    self.profile = Some(profile);
+                    self.pending_operation = PendingOperation::None;
+
+                    Command::none()
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    match err {
+                        Error::AuthError => {
+                            let login = self.login.clone();
+
+                            Command::perform(
+                                log_in(login, self.retry_policy),
+                                Message::HandleAuthResponse,
+                            )
+                        }
+                        _ => {
+                            self.notify_error(&err, Some(self.pending_operation.clone()));
+
+                            Command::none()
+                        }
+                    }
+                }
+            },
+            Message::HandleAccountProfile(result) => {
+                match result {
+                    Ok(profile) => self.profile = Some(profile),
+                    Err(err) => {
+                        eprintln!("Could not load profile: {}", err);
+                        self.track_connectivity(&err);
+                    }
+                }
+
+                Command::none()
+            }
+            Message::DeckSearchChanged(search) => {
+                self.deck_search = search;
+
+                Command::none()
+            }
+            Message::SearchDecks => {
+                let search = self.deck_search.trim().to_owned();
+                if search.is_empty() || self.offline {
+                    return Command::none();
+                }
+                self.pending_operation = PendingOperation::SearchDecks(search.clone());
+
+                Command::perform(
+                    search_decks(self.token.clone(), search, self.retry_policy),
+                    Message::HandleSearchResponse,
+                )
+            }
+            Message::HandleSearchResponse(result) => match result {
+                Ok(found) => {
+                    // Results only extend the list; the local filter narrows it.
+                    for deck in found {
+                        if !self.decks.iter().any(|d| d.id == deck.id) {
+                            self.decks.push(deck);
+                            self.fully_fetched.push(false);
+                        }
+                    }
+                    self.pending_operation = PendingOperation::None;
+
+                    Command::none()
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    match err {
+                        Error::AuthError => {
+                            let login = self.login.clone();
+
+                            Command::perform(
+                                log_in(login, self.retry_policy),
+                                Message::HandleAuthResponse,
+                            )
+                        }
+                        _ => {
+                            self.notify_error(&err, Some(self.pending_operation.clone()));
+
+                            Command::none()
+                        }
+                    }
+                }
+            },
+            Message::DeckSortChanged(sort) => {
+                self.preferences.deck_sort = sort;
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::DeckGroupingChanged(grouping) => {
+                self.preferences.deck_grouping = grouping;
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::ToggleDeckGroup(key) => {
+                if !self.preferences.collapsed_groups.remove(&key) {
+                    self.preferences.collapsed_groups.insert(key);
+                }
+                self.save_preferences();
+
+                Command::none()
+            }
+            Message::ToggleDeckFilter(filter, enabled) => {
+                match filter {
+                    DeckFilter::HasDueCards => self.deck_filters.has_due_cards = enabled,
+                    DeckFilter::OwnedByMe => self.deck_filters.owned_by_me = enabled,
+                    DeckFilter::RecentlyStudied => self.deck_filters.recently_studied = enabled,
+                }
+
+                Command::none()
+            }
+            Message::ProfileDisplayNameChanged(new_display_name) => {
+                self.profile_display_name = new_display_name;
+
+                Command::none()
+            }
+            Message::ProfileBioChanged(new_bio) => {
+                self.profile_bio = new_bio;
+
+                Command::none()
+            }
+            Message::RetryNotification(id) => {
+                let notification = match self.notifications.iter().position(|n| n.id == id) {
+                    Some(index) => self.notifications.remove(index),
+                    None => return Command::none(),
+                };
+                match notification.retry {
+                    Some(operation) => {
+                        self.pending_operation = operation.clone();
+                        self.perform(operation)
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::DismissNotification(id) => {
+                self.notifications.retain(|n| n.id != id);
+
+                Command::none()
+            }
+            Message::ExpireNotifications(now) => {
+                self.notifications.retain(|n| {
+                    n.kind == NotificationKind::Error || now - n.shown_at < NOTIFICATION_DELAY
+                });
+
+                Command::none()
+            }
+            Message::RefreshDueCounts => {
+                self.refresh_due_counts();
+
+                Command::none()
+            }
+            Message::CheckHealth => Command::perform(health_check(), Message::HandleHealthCheck),
+            Message::HandleHealthCheck(online) => {
+                if !online || !self.offline {
+                    return Command::none();
+                }
+
+                self.offline = false;
+                self.notify_success("Back online");
+
+                let command = match self.state {
+                    States::Loaded if self.token.is_empty() => Command::perform(
+                        log_in(self.login.clone(), self.retry_policy),
+                        Message::HandleAuthResponse,
+                    ),
+                    States::Loaded => self.update(Message::GetDecks),
+                    _ => self.sync_outbox(),
+                };
+                Command::batch([command, self.download_progress()])
+            }
+            Message::HandleProgressDownload(result) => match result {
+                Ok(progress) => {
+                    self.history.merge(progress.reviews, progress.card_states);
+                    self.history.synced_at = Some(progress.synced_at);
+                    self.save_history();
+
+                    self.upload_progress()
+                }
+                Err(err) => {
+                    eprintln!("Could not download progress: {}", err);
+                    self.track_connectivity(&err);
+
+                    Command::none()
+                }
+            },
+            Message::HandleProgressUpload(result) => {
+                self.uploading_progress = false;
+                match result {
+                    Ok((reviews, card_states)) => {
+                        self.history.mark_synced(&reviews, &card_states);
+                        self.save_history();
+
+                        Command::none()
+                    }
+                    Err(err) => {
+                        eprintln!("Could not upload progress: {}", err);
+                        self.track_connectivity(&err);
+
+                        Command::none()
+                    }
+                }
+            }
+            Message::SendUpdateProfile => {
+                let display_name = self.profile_display_name.clone();
+                let bio = self.profile_bio.clone();
+                self.pending_operation =
+                    PendingOperation::UpdateProfile(display_name.clone(), bio.clone());
+
+                Command::perform(
+                    update_profile(self.token.clone(), display_name, bio, self.retry_policy),
+                    Message::HandleUpdateProfileResponse,
+                )
+            }
+        }
+    }
+
+    fn view(&self) -> Element<Message> {
+        let content = match self.state {
+            States::Welcome => self.welcome_view(),
             States::Loaded => self.decks_view().into(),
             States::Details => self.deck_details_view(),
             States::Signup => self.signup_view(),
@@ -594,8 +1846,33 @@ impl Application for Shisho {
             States::Result => self.results_view(),
             States::Edit => self.edit_deck_view(),
             States::Create => self.edit_deck_view(),
+            States::Profile => self.profile_view(),
+            States::Discover => self.discover_view(),
+            States::Statistics => self.statistics_view(),
+            States::Scheduling => self.scheduling_view(),
+            States::Leeches => self.leeches_view(),
         };
-        content
+        let content = self.conflict_dialog(content);
+
+        if self.notifications.is_empty() && !self.offline {
+            return content;
+        }
+
+        let mut layered = column![];
+        if self.offline {
+            layered = layered.push(
+                container(
+                    text("Offline: Toshokan is unreachable, reconnecting...")
+                        .horizontal_alignment(Horizontal::Center)
+                        .width(Length::Fill),
+                )
+                .padding(Padding::new(5))
+                .width(Length::Fill)
+                .style(styling::wrong_card_style()),
+            );
+        }
+
+        layered.push(self.notifications_view()).push(content).into()
     }
 }
 
@@ -632,6 +1909,13 @@ fn filter_event(e: iced::Event) -> Message {
                     None => return Message::None(0),
                 }
             }
+            if key_code == KeyCode::Space || key_code == KeyCode::Enter {
+                return Message::Continue;
+            }
+            let undo = key_code == KeyCode::Z && modif.map_or(false, |m| m.command());
+            if undo || key_code == KeyCode::Backspace {
+                return Message::UndoAnswer;
+            }
             return to_answer(key_code);
         }
         None => return Message::None(0),
@@ -655,18 +1939,843 @@ fn to_answer(key: KeyCode) -> Message {
 
 impl Shisho {
     fn answer(&mut self, answer: usize) {
+        if self.exam.is_some() {
+            self.answer_exam(answer);
+            return;
+        }
         if !self.answered && answer < self.selected_answers[self.selected_card].len() {
             self.selected_answers[self.selected_card][answer] = true;
-            self.check = true;
-            self.duration = Duration::ZERO;
-            self.last_tick = Instant::now();
-            self.answered = true;
+            self.reveal();
+        }
+    }
+
+    /// Shows the feedback for the current card, which then waits for a rating.
+    fn reveal(&mut self) {
+        if self.answered {
+            return;
+        }
+        self.response_times[self.selected_card] =
+            Some(self.card_shown_at.elapsed().as_millis() as u64);
+        self.check = true;
+        self.duration = Duration::ZERO;
+        self.last_tick = Instant::now();
+        self.answered = true;
+        self.save_round();
+    }
+
+    /// Selects an answer without feedback. Unless answers can be changed,
+    /// the first one is final and moves the exam on.
+    fn answer_exam(&mut self, answer: usize) {
+        let allow_changes = match &self.exam {
+            Some(exam) if exam.time_taken.is_none() => exam.settings.allow_changes,
+            _ => return,
+        };
+        let selected = &mut self.selected_answers[self.selected_card];
+        if answer >= selected.len() || (!allow_changes && selected.contains(&true)) {
+            return;
+        }
+        selected.iter_mut().for_each(|selected| *selected = false);
+        selected[answer] = true;
+        self.response_times[self.selected_card] =
+            Some(self.card_shown_at.elapsed().as_millis() as u64);
+
+        if !allow_changes {
+            self.advance_exam();
+        }
+    }
+
+    /// Moves the exam to the next card, submitting it after the last one.
+    fn advance_exam(&mut self) {
+        if self.selected_card + 1 >= self.round_cards.len() {
+            self.submit_exam();
+            return;
+        }
+        self.selected_card += 1;
+        self.card_shown_at = Instant::now();
+        if let Some(exam) = self.exam.as_mut() {
+            exam.card_started_at = self.card_shown_at;
+        }
+    }
+
+    /// Ends the exam, shows its results and stores the attempt.
+    fn submit_exam(&mut self) {
+        let exam = match self.exam.as_mut() {
+            Some(exam) if exam.time_taken.is_none() => exam,
+            _ => return,
+        };
+        let mut time_taken = exam.started_at.elapsed();
+        if exam.settings.limit == ExamLimit::Overall {
+            time_taken = time_taken.min(exam.limit());
+        }
+        exam.time_taken = Some(time_taken);
+        let time_limit = exam.describe_limit();
+
+        self.state = States::Result;
+        self.selected_card = 0;
+        self.check = true;
+        self.duration = Duration::ZERO;
+        self.last_tick = Instant::now();
+        self.answered = self.preferences.animate_score;
+        if !self.preferences.animate_score {
+            self.duration = RESULTS_DELAY;
+        }
+        self.score = self.round_score();
+
+        let deck_id = self.round_decks[0].clone();
+        let deck_title = self
+            .decks
+            .iter()
+            .find(|deck| deck.id == deck_id)
+            .map(|deck| deck.title.clone())
+            .unwrap_or_default();
+        let correct_cards = (0..self.round_cards.len())
+            .filter(|index| self.round_card_correct(*index))
+            .count();
+        self.history.record_exam(ExamAttempt {
+            deck_id,
+            deck_title,
+            finished_at: chrono::Utc::now(),
+            cards: self.round_cards.len(),
+            correct_cards,
+            time_taken_ms: time_taken.as_millis() as u64,
+            time_limit,
+        });
+        self.save_history();
+    }
+
+    fn answer_delay(&self) -> Duration {
+        Duration::from_millis(self.preferences.answer_delay_ms)
+    }
+
+    /// Moves past the current, rated card, finishing the round after the
+    /// last one.
+    fn next_card(&mut self) -> Command<Message> {
+        if self.selected_card + 1 < self.round_cards.len() {
+            self.selected_card += 1;
+            self.card_shown_at = Instant::now();
+            self.check = false;
+            self.answered = false;
+            self.save_round();
+            return Command::none();
+        }
+
+        if self.exam.is_none() {
+            storage::remove(&self.login.username, storage::ROUND_FILE);
+        }
+
+        self.state = States::Result;
+        self.duration = Duration::ZERO;
+        self.last_tick = Instant::now();
+        if !self.preferences.animate_score {
+            self.duration = RESULTS_DELAY;
+            self.answered = false;
+        }
+        self.selected_card = 0;
+        self.score = self.round_score();
+        self.record_round();
+        Command::batch([self.upload_progress(), self.sync_outbox()])
+    }
+
+    fn rate(&mut self, rating: Rating) {
+        if self.answered && self.round_ratings[self.selected_card].is_none() {
+            self.round_ratings[self.selected_card] = Some(rating);
+            self.save_round();
+        }
+    }
+
+    /// Takes back the answer to the current card, or to the previous card
+    /// while the current one is unanswered. Rounds only schedule their cards
+    /// once finished, so clearing the answer reverts everything; the undo is
+    /// still logged for the statistics.
+    fn undo_answer(&mut self) {
+        if !self.answered {
+            if self.selected_card == 0 {
+                return;
+            }
+            self.selected_card -= 1;
+        }
+
+        let index = self.selected_card;
+        let correct = self.round_card_correct(index);
+        self.history.record_undo(
+            &self.round_decks[index],
+            &self.round_cards[index].id,
+            correct,
+        );
+        self.save_history();
+
+        self.selected_answers[index]
+            .iter_mut()
+            .for_each(|selected| *selected = false);
+        self.response_times[index] = None;
+        self.round_ratings[index] = None;
+        self.check = false;
+        self.answered = false;
+        self.duration = Duration::ZERO;
+        self.card_shown_at = Instant::now();
+        self.save_round();
+    }
+
+    fn saved_round(&self) -> SavedRound {
+        SavedRound {
+            title: self.round_title.clone(),
+            cards: self.round_cards.clone(),
+            deck_ids: self.round_decks.clone(),
+            selected_card: self.selected_card,
+            selected_answers: self.selected_answers.clone(),
+            response_times: self.response_times.clone(),
+            ratings: self.round_ratings.clone(),
+            flashcards: self.flashcards,
+            card_elapsed_ms: self.card_shown_at.elapsed().as_millis() as u64,
+            saved_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Keeps the round in progress on disk, so that closing the app does
+    /// not lose it. Exams are left out, as their clock cannot be paused.
+    fn save_round(&self) {
+        if self.exam.is_none() {
+            storage::save(
+                &self.login.username,
+                storage::ROUND_FILE,
+                &self.saved_round(),
+            );
+        }
+    }
+
+    /// Picks a saved round up at the card it was left on, showing the
+    /// feedback again when that card was already answered.
+    fn resume_round(&mut self, round: SavedRound) {
+        let answered = round.response_times[round.selected_card].is_some();
+        self.round_title = round.title;
+        self.round_cards = round.cards;
+        self.round_decks = round.deck_ids;
+        self.selected_card = round.selected_card;
+        self.selected_answers = round.selected_answers;
+        self.response_times = round.response_times;
+        self.round_ratings = round.ratings;
+        self.flashcards = round.flashcards;
+        self.exam = None;
+        // Resumed rounds are started from the deck list, whatever deck is
+        // selected by then.
+        self.round_return = TargetView::Welcome;
+        self.card_shown_at = Instant::now()
+            .checked_sub(Duration::from_millis(round.card_elapsed_ms))
+            .unwrap_or_else(Instant::now);
+        self.check = answered;
+        self.answered = answered;
+        self.duration = Duration::ZERO;
+        self.last_tick = Instant::now();
+        self.state = States::Round;
+    }
+
+    fn leave_round(&mut self, target: TargetView) {
+        self.selected_card = 0;
+        self.check = false;
+        self.duration = Duration::ZERO;
+        self.last_tick = Instant::now();
+        self.answered = false;
+        self.exam = None;
+        self.state = match target {
+            TargetView::Details => States::Details,
+            TargetView::Welcome => States::Loaded,
+        };
+    }
+
+    /// Whether the round card at `index` counts as known: in flashcard
+    /// rounds the learner's rating decides, otherwise the chosen answers.
+    fn round_card_correct(&self, index: usize) -> bool {
+        if self.flashcards {
+            return self.round_ratings[index].map_or(false, |rating| rating.is_recalled());
+        }
+        card_is_correct(
+            &self.round_cards[index].answers,
+            &self.selected_answers[index],
+        )
+    }
+
+    fn round_score(&self) -> f32 {
+        let correct_cards = (0..self.round_cards.len())
+            .filter(|index| self.round_card_correct(*index))
+            .count();
+        (correct_cards as f32 / self.round_cards.len() as f32) * 100.0
+    }
+
+    /// Shows the deck list once the decks first load after logging in.
+    /// Later refreshes leave the user on the screen they are on.
+    fn leave_loading_screen(&mut self) {
+        if let States::Welcome | States::Signup = self.state {
+            self.state = States::Loaded;
+        }
+    }
+
+    fn perform(&mut self, operation: PendingOperation) -> Command<Message> {
+        match operation {
+            PendingOperation::GetDecks => Command::perform(
+                get_decks(self.token.clone(), self.retry_policy),
+                Message::HandleDecksResponse,
+            ),
+            PendingOperation::GetDeck(id) => Command::perform(
+                get_deck(self.token.clone(), id, self.retry_policy),
+                Message::HandleDeckResponse,
+            ),
+            PendingOperation::SyncOutbox => self.sync_outbox(),
+            PendingOperation::GetPublicDecks(search) => Command::perform(
+                get_public_decks(self.token.clone(), search, self.retry_policy),
+                Message::HandlePublicDecksResponse,
+            ),
+            PendingOperation::GetPublicDeck(index) => {
+                self.update(Message::PreviewPublicDeck(index))
+            }
+            PendingOperation::SearchDecks(search) => Command::perform(
+                search_decks(self.token.clone(), search, self.retry_policy),
+                Message::HandleSearchResponse,
+            ),
+            PendingOperation::GetProfile => Command::perform(
+                get_profile(self.token.clone(), self.retry_policy),
+                Message::HandleProfileResponse,
+            ),
+            PendingOperation::UpdateProfile(display_name, bio) => Command::perform(
+                update_profile(self.token.clone(), display_name, bio, self.retry_policy),
+                Message::HandleUpdateProfileResponse,
+            ),
+            PendingOperation::None => Command::none(),
+        }
+    }
+
+    fn load_local_data(&mut self) {
+        self.history = self.load_file(storage::HISTORY_FILE).unwrap_or_default();
+        self.deck_cache = self.load_file(storage::DECK_CACHE_FILE).unwrap_or_default();
+        self.outbox = self.load_file(storage::OUTBOX_FILE).unwrap_or_default();
+        self.preferences = self
+            .load_file(storage::PREFERENCES_FILE)
+            .unwrap_or_default();
+        self.retry_policy = self.preferences.retry_policy();
+        self.daily_goal_input = self.preferences.daily_goal.to_string();
+        self.answer_delay_input = format_seconds(self.preferences.answer_delay_ms);
+        self.exam_seconds_input = self.preferences.exam.seconds.to_string();
+        self.paused_round = self.load_file(storage::ROUND_FILE);
+        if !self
+            .paused_round
+            .as_ref()
+            .map_or(true, SavedRound::is_consistent)
+        {
+            self.paused_round = None;
+            storage::remove(&self.login.username, storage::ROUND_FILE);
+        }
+        self.refresh_due_counts();
+    }
+
+    /// Reads one of the user's saved files. Unreadable files have been moved
+    /// aside by `storage`, and the user is told where to find them.
+    fn load_file<T: DeserializeOwned>(&mut self, file: &str) -> Option<T> {
+        match storage::load(&self.login.username, file) {
+            Ok(value) => value,
+            Err(err) => {
+                eprintln!("{}", err);
+                self.notify(NotificationKind::Error, err.to_string(), None);
+                None
+            }
+        }
+    }
+
+    /// Changes the settings of the selected deck and saves them.
+    fn update_deck_settings(&mut self, update: impl FnOnce(&mut DeckSettings)) {
+        let deck_id = self.decks[self.selected_deck].id.clone();
+        update(self.preferences.deck_settings.entry(deck_id).or_default());
+        self.save_preferences();
+        self.refresh_due_counts();
+    }
+
+    /// FSRS with the weights fitted to this user, or the defaults.
+    fn fsrs(&self) -> Fsrs {
+        self.preferences.fsrs.clone().unwrap_or_default()
+    }
+
+    fn save_preferences(&self) {
+        storage::save(
+            &self.login.username,
+            storage::PREFERENCES_FILE,
+            &self.preferences,
+        );
+    }
+
+    /// Shows the cached deck list, returning whether there was one.
+    fn load_cached_decks(&mut self) -> bool {
+        if self.deck_cache.decks.is_empty() {
+            return false;
+        }
+
+        let (decks, fully_fetched) = self.deck_cache.decks();
+        self.decks = decks;
+        self.fully_fetched = fully_fetched;
+        self.already_selected = false;
+        self.refresh_due_counts();
+        true
+    }
+
+    fn save_outbox(&self) {
+        storage::save(&self.login.username, storage::OUTBOX_FILE, &self.outbox);
+    }
+
+    /// Adds a deck made on this machine to the library and queues its upload.
+    fn add_local_deck(&mut self, mut deck: Deck) -> usize {
+        deck.id = outbox::local_id();
+        deck.created_at = Some(chrono::Utc::now());
+        if let Some(profile) = &self.profile {
+            deck.owner_id = profile.id.clone();
+        }
+        self.outbox.push(Mutation::Create { deck: deck.clone() });
+        self.deck_cache.store_deck(&deck);
+        self.decks.push(deck);
+        self.fully_fetched.push(true);
+        self.decks.len() - 1
+    }
+
+    /// Sends the oldest queued mutation, unless one is already on its way or
+    /// the queue is blocked by an unresolved conflict.
+    fn sync_outbox(&mut self) -> Command<Message> {
+        self.replay_front(false)
+    }
+
+    fn replay_front(&mut self, force: bool) -> Command<Message> {
+        if self.outbox.in_flight || self.offline || self.conflict.is_some() || self.token.is_empty()
+        {
+            return Command::none();
+        }
+
+        match self.outbox.front() {
+            Some(mutation) => {
+                let mutation = mutation.clone();
+                self.outbox.in_flight = true;
+                Command::perform(
+                    outbox::replay(self.token.clone(), mutation, force, self.retry_policy),
+                    Message::HandleOutboxReplay,
+                )
+            }
+            None => Command::none(),
+        }
+    }
+
+    /// Swaps the local copy of a synced deck for the one the server created.
+    fn apply_synced(&mut self, mutation: &Mutation, created: Option<Deck>) {
+        let (sent, created) = match (mutation.deck(), created) {
+            (Some(sent), Some(created)) => (sent, created),
+            _ => {
+                self.notify_success(&format!("Deck \"{}\" deleted", mutation.title()));
+                return;
+            }
+        };
+
+        let old_id = mutation.local_deck_id();
+        self.history.rekey_deck(old_id, sent, &created);
+        self.save_history();
+        self.preferences.rekey_deck(old_id, &created.id);
+        self.save_preferences();
+        self.outbox.rebase(old_id, &created);
+        self.deck_cache.remove(old_id);
+        self.deck_cache.store_deck(&created);
+        self.save_deck_cache();
+        if let Some(index) = self.decks.iter().position(|d| d.id == old_id) {
+            self.decks[index] = created;
+            self.fully_fetched[index] = true;
+        }
+        self.notify_success(&format!("Deck \"{}\" saved", mutation.title()));
+    }
+
+    fn resolve_conflict(&mut self, conflict: Conflict, resolution: Resolution) -> Command<Message> {
+        match (conflict, resolution) {
+            (Conflict::EditedElsewhere { .. }, Resolution::KeepMine) => {
+                return self.replay_front(true);
+            }
+            (Conflict::DeletedOnServer { mutation }, Resolution::KeepMine) => {
+                if let Some(deck) = mutation.deck() {
+                    self.outbox
+                        .replace_front(Mutation::Create { deck: deck.clone() });
+                }
+            }
+            (Conflict::EditedElsewhere { mutation, server }, Resolution::KeepBoth) => {
+                // Ours goes up as a separate deck next to the server's version.
+                if let Some(deck) = mutation.deck() {
+                    let mut deck = deck.clone();
+                    deck.id = outbox::local_id();
+                    if let Some(index) = self.decks.iter().position(|d| d.id == server.id) {
+                        self.decks[index].id = deck.id.clone();
+                    }
+                    self.deck_cache.remove(&server.id);
+                    self.deck_cache.store_deck(&deck);
+                    self.outbox.replace_front(Mutation::Create { deck });
+                }
+                self.restore_server_deck(server);
+            }
+            (Conflict::EditedElsewhere { server, .. }, _) => {
+                self.outbox.pop_front();
+                self.restore_server_deck(server);
+            }
+            (Conflict::DeletedOnServer { mutation }, _) => {
+                self.outbox.pop_front();
+                let id = mutation.local_deck_id();
+                if let Some(index) = self.decks.iter().position(|d| d.id == id) {
+                    self.decks.remove(index);
+                    self.fully_fetched.remove(index);
+                    self.already_selected = false;
+                    if let States::Details = self.state {
+                        self.state = States::Loaded;
+                    }
+                }
+                self.deck_cache.remove(id);
+            }
         }
+        self.save_deck_cache();
+        self.save_outbox();
+
+        self.sync_outbox()
+    }
+
+    fn restore_server_deck(&mut self, server: Deck) {
+        self.deck_cache.store_deck(&server);
+        match self.decks.iter().position(|d| d.id == server.id) {
+            Some(index) => {
+                self.decks[index] = server;
+                self.fully_fetched[index] = true;
+            }
+            None => {
+                self.decks.push(server);
+                self.fully_fetched.push(true);
+            }
+        }
+    }
+
+    /// Saves the deck cache, recounting due cards as the decks changed.
+    fn save_deck_cache(&mut self) {
+        storage::save(
+            &self.login.username,
+            storage::DECK_CACHE_FILE,
+            &self.deck_cache,
+        );
+        self.refresh_due_counts();
+    }
+
+    /// Saves the history, recounting due cards as reviews and card states
+    /// changed.
+    fn save_history(&mut self) {
+        storage::save(&self.login.username, storage::HISTORY_FILE, &self.history);
+        self.refresh_due_counts();
+    }
+
+    fn refresh_due_counts(&mut self) {
+        let now = chrono::Utc::now();
+        self.due_counts = self
+            .decks
+            .iter()
+            .map(|deck| {
+                let settings = self.preferences.deck_settings(&deck.id);
+                (
+                    deck.id.clone(),
+                    self.history.due_counts(deck, &settings, now),
+                )
+            })
+            .collect();
+    }
+
+    fn track_connectivity(&mut self, err: &Error) {
+        if let Error::NetworkError(_) = err {
+            self.offline = true;
+        }
+    }
+
+    fn notify_error(&mut self, err: &Error, retry: Option<PendingOperation>) {
+        self.track_connectivity(err);
+
+        let retry = match retry {
+            Some(PendingOperation::None) => None,
+            retry => retry,
+        };
+
+        self.notify(NotificationKind::Error, err.to_string(), retry);
+    }
+
+    fn notify_success(&mut self, message: &str) {
+        self.notify(NotificationKind::Success, message.to_owned(), None);
+    }
+
+    fn notify(&mut self, kind: NotificationKind, message: String, retry: Option<PendingOperation>) {
+        self.notifications.push(Notification {
+            id: self.next_notification_id,
+            kind,
+            message,
+            retry,
+            shown_at: Instant::now(),
+        });
+        self.next_notification_id += 1;
+    }
+
+    fn notifications_view(&self) -> Element<Message> {
+        self.notifications
+            .iter()
+            .fold(
+                column![].spacing(5).padding(Padding::from([5, 20])),
+                |notifications, notification| {
+                    let mut notification_row = row![text(notification.message.clone())
+                        .width(Length::Fill)
+                        .vertical_alignment(Vertical::Center)]
+                    .spacing(10)
+                    .align_items(Alignment::Center);
+
+                    if notification.retry.is_some() {
+                        notification_row = notification_row.push(
+                            button(text("Retry"))
+                                .on_press(Message::RetryNotification(notification.id)),
+                        );
+                    }
+                    notification_row = notification_row.push(
+                        button(text("Dismiss"))
+                            .on_press(Message::DismissNotification(notification.id)),
+                    );
+
+                    let style = match notification.kind {
+                        NotificationKind::Error => styling::wrong_card_style(),
+                        NotificationKind::Success => styling::correct_card_style(),
+                    };
+
+                    notifications.push(
+                        container(notification_row.padding(Padding::new(10)))
+                            .width(Length::Fill)
+                            .style(style),
+                    )
+                },
+            )
+            .into()
+    }
+
+    /// Starts a round over the cards of the decks at `indices`, keeping only
+    /// those matching `filter` when one is given. Only exams start while a
+    /// round is paused, as the new round would take the paused one's place.
+    fn start_round(
+        &mut self,
+        indices: &[usize],
+        title: String,
+        return_to: TargetView,
+        filter: Option<&TagFilter>,
+        kind: RoundKind,
+    ) {
+        if kind != RoundKind::Exam && self.paused_round.is_some() {
+            self.notify_success("Resume or discard the paused round first");
+            return;
+        }
+
+        let now = chrono::Utc::now();
+        let include = |card: &Card| filter.map_or(true, |filter| filter.matches(&card.tags));
+        let mut cards = Vec::new();
+        let mut deck_ids = Vec::new();
+        for index in indices.iter().copied() {
+            let deck = &self.decks[index];
+            let card_indices: Vec<usize> = match kind {
+                RoundKind::Scheduled => {
+                    let settings = self.preferences.deck_settings(&deck.id);
+                    self.history.scheduled_cards(deck, &settings, now, include)
+                }
+                RoundKind::Cram | RoundKind::Exam => (0..deck.cards.len())
+                    .filter(|card| include(&deck.cards[*card]))
+                    .filter(|card| !self.history.is_suspended(&deck.cards[*card].id))
+                    .collect(),
+            };
+            for card in card_indices {
+                cards.push(deck.cards[card].clone());
+                deck_ids.push(deck.id.clone());
+            }
+        }
+        if cards.is_empty() {
+            self.notify_success(match (kind, filter) {
+                (RoundKind::Scheduled, None) => "Nothing is due right now",
+                (RoundKind::Scheduled, Some(_)) => "No due cards match the tag filter",
+                (RoundKind::Cram | RoundKind::Exam, _) => "There are no cards to study",
+            });
+            return;
+        }
+
+        self.selected_answers = cards
+            .iter()
+            .map(|card| vec![false; card.answers.len()])
+            .collect();
+        self.round_title = title;
+        self.round_cards = cards;
+        self.response_times = vec![None; self.round_cards.len()];
+        self.round_ratings = vec![None; self.round_cards.len()];
+        self.flashcards = kind != RoundKind::Exam && self.preferences.flashcard_mode;
+        self.exam = match kind {
+            RoundKind::Exam => Some(Exam::new(self.preferences.exam.clone())),
+            _ => None,
+        };
+        self.round_decks = deck_ids;
+        self.round_return = return_to;
+        self.selected_card = 0;
+        self.card_shown_at = Instant::now();
+        self.check = false;
+        self.answered = false;
+        self.state = States::Round;
+        self.save_round();
+    }
+
+    /// Indices of the decks filed in the folder at `path` or below it.
+    fn decks_in_folder(&self, path: &[String]) -> Vec<usize> {
+        self.decks
+            .iter()
+            .enumerate()
+            .filter(|(_, deck)| folders::is_within(&deck.folder, path))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn start_folder_round(&mut self, path: &[String]) {
+        let indices = self.decks_in_folder(path);
+        let title = folders::format_path(path);
+        self.start_round(
+            &indices,
+            title,
+            TargetView::Welcome,
+            None,
+            RoundKind::Scheduled,
+        );
+    }
+
+    /// Records the finished round once per deck its cards came from.
+    fn record_round(&mut self) {
+        let mut new_leeches = 0;
+        let mut deck_ids: Vec<&String> = Vec::new();
+        for deck_id in self.round_decks.iter() {
+            if !deck_ids.contains(&deck_id) {
+                deck_ids.push(deck_id);
+            }
+        }
+
+        for deck_id in deck_ids {
+            let deck = match self.decks.iter().find(|d| &d.id == deck_id) {
+                Some(deck) => deck,
+                None => continue,
+            };
+            let scheduler = self
+                .preferences
+                .deck_settings(deck_id)
+                .scheduler
+                .scheduler(&self.fsrs());
+            let settings = self.preferences.deck_settings(deck_id);
+            let answers: Vec<AnsweredCard> = self
+                .round_cards
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| &self.round_decks[*index] == deck_id)
+                .map(|(index, card)| {
+                    let correct = self.round_card_correct(index);
+                    AnsweredCard {
+                        card_id: card.id.clone(),
+                        correct,
+                        response_ms: self.response_times[index],
+                        rating: self.round_ratings[index]
+                            .unwrap_or_else(|| Rating::from_correct(correct)),
+                    }
+                })
+                .collect();
+
+            let leeches = self
+                .history
+                .record_round(deck, &answers, scheduler.as_ref(), &settings);
+            if settings.leech_action == LeechAction::Mark {
+                new_leeches += leeches.len();
+            }
+        }
+        self.save_history();
+
+        if new_leeches > 0 {
+            self.notify_success(&format!(
+                "{} new leech{}, see the deck's leeches",
+                new_leeches,
+                if new_leeches == 1 { "" } else { "es" }
+            ));
+        }
+    }
+
+    /// Adds the leech tag to cards of a deck of this user, queueing the edit
+    /// like any other. Only done when the user asks, since the edit gives
+    /// the deck and its cards new ids on Toshokan.
+    fn tag_leeches(&mut self, deck_id: &str, card_ids: &[String]) {
+        let index = match self.decks.iter().position(|d| d.id == deck_id) {
+            Some(index) => index,
+            None => return,
+        };
+        let owned = self
+            .profile
+            .as_ref()
+            .map_or(false, |profile| self.decks[index].owner_id == profile.id);
+        if !owned {
+            return;
+        }
+
+        let base = self.decks[index].clone();
+        let mut deck = base.clone();
+        for card in deck.cards.iter_mut() {
+            let tagged = card.tags.iter().any(|t| t.eq_ignore_ascii_case(LEECH_TAG));
+            if card_ids.contains(&card.id) && !tagged {
+                card.tags.push(LEECH_TAG.to_owned());
+            }
+        }
+        self.outbox.push(Mutation::Update {
+            base,
+            deck: deck.clone(),
+        });
+        self.deck_cache.store_deck(&deck);
+        self.decks[index] = deck;
+        self.save_outbox();
+        self.save_deck_cache();
+    }
+
+    /// Fetches progress recorded on other machines since the last download.
+    fn download_progress(&self) -> Command<Message> {
+        if self.token.is_empty() || self.offline {
+            return Command::none();
+        }
+
+        Command::perform(
+            get_progress(
+                self.token.clone(),
+                self.history.synced_at.clone(),
+                self.retry_policy,
+            ),
+            Message::HandleProgressDownload,
+        )
+    }
+
+    /// Sends reviews and card states Toshokan has not seen yet. Decks still
+    /// waiting in the outbox are left out until they have their final ids.
+    fn upload_progress(&mut self) -> Command<Message> {
+        if self.token.is_empty() || self.offline || self.uploading_progress {
+            return Command::none();
+        }
+
+        let outbox = &self.outbox;
+        let (reviews, card_states) = self.history.unsynced(|deck_id| outbox.touches(deck_id));
+        if reviews.is_empty() && card_states.is_empty() {
+            return Command::none();
+        }
+
+        self.uploading_progress = true;
+        let token = self.token.clone();
+        let retry = self.retry_policy;
+        Command::perform(
+            async move {
+                record_progress(token, reviews.clone(), card_states.clone(), retry)
+                    .await
+                    .map(|_| (reviews, card_states))
+            },
+            Message::HandleProgressUpload,
+        )
     }
 
     fn select_deck(&mut self, index: usize) -> iced::Command<Message> {
         self.already_selected = true;
         self.selected_deck = index;
+        self.deck_settings_form =
+            DeckSettingsForm::from(&self.preferences.deck_settings(&self.decks[index].id));
         match self.fully_fetched[index] {
             true => {
                 self.state = States::Details;
@@ -675,7 +2784,11 @@ impl Shisho {
             false => {
                 self.pending_operation = PendingOperation::GetDeck(self.decks[index].id.clone());
                 Command::perform(
-                    get_deck(self.token.clone(), self.decks[index].id.clone()),
+                    get_deck(
+                        self.token.clone(),
+                        self.decks[index].id.clone(),
+                        self.retry_policy,
+                    ),
                     Message::HandleDeckResponse,
                 )
             }
@@ -803,53 +2916,211 @@ impl Shisho {
             .into()
     }
 
-    fn decks_view(&self) -> Element<Message> {
+    /// Indices of the decks matching the search box and the enabled filters.
+    fn visible_decks(&self) -> Vec<usize> {
+        let search = self.deck_search.trim().to_lowercase();
+        let now = chrono::Utc::now();
+        let recent = now - chrono::Duration::days(RECENTLY_STUDIED_DAYS);
+        let filters = self.deck_filters;
+
+        let mut visible = self
+            .decks
+            .iter()
+            .enumerate()
+            .filter(|(_, deck)| {
+                search.is_empty()
+                    || deck.title.to_lowercase().contains(&search)
+                    || deck.description.to_lowercase().contains(&search)
+            })
+            .filter(|(_, deck)| !filters.has_due_cards || self.due_counts(deck).total() > 0)
+            .filter(|(_, deck)| {
+                // Ownership is unknown until the profile has been loaded.
+                !filters.owned_by_me
+                    || self
+                        .profile
+                        .as_ref()
+                        .map_or(true, |profile| deck.owner_id == profile.id)
+            })
+            .filter(|(_, deck)| {
+                !filters.recently_studied
+                    || self
+                        .history
+                        .last_studied(&deck.id)
+                        .map_or(false, |studied| studied >= recent)
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        self.sort_decks(&mut visible);
+        visible
+    }
+
+    /// What the deck holds to study today under its daily limits, as shown
+    /// on its badge and used by the due filter and sort. Decks added since
+    /// the last count are counted on the spot.
+    fn due_counts(&self, deck: &Deck) -> DueCounts {
+        match self.due_counts.get(&deck.id) {
+            Some(counts) => *counts,
+            None => {
+                let settings = self.preferences.deck_settings(&deck.id);
+                self.history.due_counts(deck, &settings, chrono::Utc::now())
+            }
+        }
+    }
+
+    /// Orders deck indices by the chosen sort. Dates and counts put the
+    /// largest first; decks lacking the value go last.
+    fn sort_decks(&self, indices: &mut [usize]) {
+        let decks = &self.decks;
+
+        match self.preferences.deck_sort {
+            DeckSort::Popularity => {}
+            DeckSort::Title => {
+                indices.sort_by_cached_key(|index| decks[*index].title.to_lowercase())
+            }
+            DeckSort::Created => {
+                indices.sort_by_key(|index| std::cmp::Reverse(decks[*index].created_at))
+            }
+            DeckSort::LastStudied => indices.sort_by_cached_key(|index| {
+                std::cmp::Reverse(self.history.last_studied(&decks[*index].id))
+            }),
+            DeckSort::DueCards => indices.sort_by_cached_key(|index| {
+                std::cmp::Reverse(self.due_counts(&decks[*index]).total())
+            }),
+            DeckSort::CardCount => {
+                indices.sort_by_key(|index| std::cmp::Reverse(decks[*index].card_count))
+            }
+        }
+    }
+
+    /// Splits sorted deck indices into one section per tag. A deck with
+    /// several tags shows up under each of them.
+    fn group_decks_by_tag(&self, indices: &[usize]) -> Vec<(String, Vec<usize>)> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        let mut add = |name: &str, index: usize| match groups.iter_mut().find(|g| g.0 == name) {
+            Some(group) => group.1.push(index),
+            None => groups.push((name.to_owned(), vec![index])),
+        };
+
+        for index in indices.iter().copied() {
+            let deck = &self.decks[index];
+            if deck.tags.is_empty() {
+                add("Untagged", index);
+            }
+            for tag in deck.tags.iter() {
+                add(tag, index);
+            }
+        }
+
+        groups.sort_by_cached_key(|(name, _)| name.to_lowercase());
+        groups
+    }
+
+    /// Renders a folder's subfolders as collapsible sections, each able to
+    /// start a round over everything below it, followed by its own decks.
+    fn folder_view(&self, folder: Folder) -> Element<Message> {
+        let mut content = column![].spacing(15);
+
+        for subfolder in folder.folders {
+            let key = format!("Folder:{}", folders::format_path(&subfolder.path));
+            let collapsed = self.preferences.collapsed_groups.contains(&key);
+            let marker = if collapsed { "+" } else { "-" };
+            let header = row![
+                button(
+                    text(format!(
+                        "{} {} ({})",
+                        marker,
+                        subfolder.name,
+                        subfolder.deck_count()
+                    ))
+                    .size(22)
+                )
+                .on_press(Message::ToggleDeckGroup(key))
+                .style(styling::invisible_button()),
+                button(text("Study").size(15))
+                    .on_press(Message::StudyFolder(subfolder.path.clone())),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+            content = content.push(header);
+            if !collapsed {
+                content = content.push(
+                    container(self.folder_view(subfolder)).padding(Padding::from([0, 0, 0, 20])),
+                );
+            }
+        }
+
+        if !folder.decks.is_empty() {
+            content = content.push(self.deck_grid(&folder.decks));
+        }
+
+        content.into()
+    }
+
+    fn deck_grid(&self, indices: &[usize]) -> Element<Message> {
         let mut left_column = column![].spacing(15).max_width(200);
         let mut right_column = column![].spacing(15).max_width(200);
 
-        for (index, deck) in self.decks.iter().enumerate() {
-            if index % 2 == 0 {
-                left_column = left_column.push(
-                    button(
-                        container(
-                            column![text(deck.title.clone()), text(deck.description.clone())]
-                                .padding(10)
-                                .spacing(15),
-                        )
-                        .style(styling::card_style())
-                        .height(iced::Length::Units(115))
-                        .width(iced::Length::Units(200)),
-                    )
-                    .on_press(Message::SelectDeck(index))
-                    .style(styling::invisible_button()),
-                );
+        for (position, index) in indices.iter().copied().enumerate() {
+            let deck = &self.decks[index];
+            let deck_button = button(
+                container(self.deck_card(deck))
+                    .style(styling::card_style())
+                    .height(iced::Length::Units(115))
+                    .width(iced::Length::Units(200)),
+            )
+            .on_press(Message::SelectDeck(index))
+            .style(styling::invisible_button());
+
+            if position % 2 == 0 {
+                left_column = left_column.push(deck_button);
             } else {
-                right_column = right_column.push(
-                    button(
-                        container(
-                            column![text(deck.title.clone()), text(deck.description.clone())]
-                                .padding(10)
-                                .spacing(15),
-                        )
-                        .style(styling::card_style())
-                        .height(iced::Length::Units(115))
-                        .width(iced::Length::Units(200)),
-                    )
-                    .on_press(Message::SelectDeck(index))
-                    .style(styling::invisible_button()),
-                );
+                right_column = right_column.push(deck_button);
             }
         }
 
-        let columns_row = row![left_column, right_column].spacing(15);
-        let decks_scroll = column![scrollable(column![columns_row])
-            .scrollbar_width(5)
-            .scroller_width(5),];
+        row![left_column, right_column].spacing(15).into()
+    }
+
+    fn decks_view(&self) -> Element<Message> {
+        let visible = self.visible_decks();
+        let mut decks_list = column![].spacing(15);
+        match self.preferences.deck_grouping {
+            DeckGrouping::None => decks_list = decks_list.push(self.deck_grid(&visible)),
+            DeckGrouping::Folder => {
+                decks_list = decks_list.push(self.folder_view(Folder::tree(&self.decks, &visible)))
+            }
+            DeckGrouping::Tag => {
+                for (name, indices) in self.group_decks_by_tag(&visible) {
+                    let key = format!("Tag:{}", name);
+                    let collapsed = self.preferences.collapsed_groups.contains(&key);
+                    let marker = if collapsed { "+" } else { "-" };
+                    decks_list = decks_list.push(
+                        button(text(format!("{} {} ({})", marker, name, indices.len())).size(22))
+                            .on_press(Message::ToggleDeckGroup(key))
+                            .style(styling::invisible_button()),
+                    );
+                    if !collapsed {
+                        decks_list = decks_list.push(self.deck_grid(&indices));
+                    }
+                }
+            }
+        }
+        if visible.is_empty() && !self.decks.is_empty() {
+            decks_list = decks_list.push(text("No decks match the search and filters"));
+        }
+        let decks_scroll = column![scrollable(decks_list).scrollbar_width(5).scroller_width(5),];
         let shisho_text = row![shisho_text()].padding(Padding::from([0, 0, 15, 0]));
         let decks_title = row![
-            column![button(refresh_icon()).on_press(Message::GetDecks)]
-                .width(iced::Length::Fill)
-                .align_items(Alignment::Start),
+            column![row![
+                button(refresh_icon()).on_press(Message::GetDecks),
+                button("Profile").on_press(Message::ShowProfile),
+                button("Discover").on_press(Message::ShowDiscover)
+            ]
+            .spacing(5)
+            .align_items(Alignment::Center)]
+            .width(iced::Length::Fill)
+            .align_items(Alignment::Start),
             column![text("Decks").size(28)]
                 .width(iced::Length::Fill)
                 .align_items(Alignment::Center),
@@ -859,9 +3130,84 @@ impl Shisho {
         ]
         .width(iced::Length::Units(400));
 
-        let content = column![shisho_text, decks_title, decks_scroll]
+        let mut search_button = button("Search Toshokan");
+        if !self.offline && !self.deck_search.trim().is_empty() {
+            search_button = search_button.on_press(Message::SearchDecks);
+        }
+        let search_row = row![
+            text_input(
+                "Search decks",
+                &self.deck_search,
+                Message::DeckSearchChanged
+            )
+            .on_submit(Message::SearchDecks)
+            .padding(5),
+            search_button
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center)
+        .width(iced::Length::Units(400));
+        let filters = self.deck_filters;
+        let filters_row = row![
+            checkbox("Has due cards", filters.has_due_cards, |enabled| {
+                Message::ToggleDeckFilter(DeckFilter::HasDueCards, enabled)
+            })
+            .size(16),
+            checkbox("Owned by me", filters.owned_by_me, |enabled| {
+                Message::ToggleDeckFilter(DeckFilter::OwnedByMe, enabled)
+            })
+            .size(16),
+            checkbox("Recently studied", filters.recently_studied, |enabled| {
+                Message::ToggleDeckFilter(DeckFilter::RecentlyStudied, enabled)
+            })
+            .size(16),
+        ]
+        .spacing(15)
+        .width(iced::Length::Units(400));
+        let order_row = row![
+            pick_list(
+                &DeckSort::ALL[..],
+                Some(self.preferences.deck_sort),
+                Message::DeckSortChanged
+            ),
+            pick_list(
+                &DeckGrouping::ALL[..],
+                Some(self.preferences.deck_grouping),
+                Message::DeckGroupingChanged
+            ),
+        ]
+        .spacing(10)
+        .width(iced::Length::Units(400));
+
+        let mut content = column![shisho_text, self.study_calendar()]
             .align_items(Alignment::Center)
             .spacing(30);
+        if let Some(round) = &self.paused_round {
+            content = content.push(
+                row![
+                    text(format!(
+                        "Paused {} at card {}/{}, {}",
+                        round.title,
+                        round.selected_card + 1,
+                        round.cards.len(),
+                        round
+                            .saved_at
+                            .with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                    )),
+                    button("Resume").on_press(Message::ResumeRound),
+                    button("Discard")
+                        .on_press(Message::DiscardPausedRound)
+                        .style(iced::theme::Button::Destructive)
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+        let content = content
+            .push(decks_title)
+            .push(column![search_row, filters_row, order_row].spacing(10))
+            .push(decks_scroll);
 
         container(content)
             .width(Length::Fill)
@@ -870,6 +3216,87 @@ impl Shisho {
             .into()
     }
 
+    /// Reviews per day over the past year, with streaks and today's goal.
+    fn study_calendar(&self) -> Element<Message> {
+        let today = chrono::Local::now().date_naive();
+        let per_day = self.history.reviews_per_day();
+        let year_ago = today - chrono::Duration::weeks(52);
+        let start =
+            year_ago - chrono::Duration::days(year_ago.weekday().num_days_from_monday() as i64);
+        let counts: Vec<usize> = (0..=(today - start).num_days())
+            .map(|offset| {
+                let day = start + chrono::Duration::days(offset);
+                per_day.get(&day).copied().unwrap_or(0)
+            })
+            .collect();
+        let weeks = (counts.len() + 6) / 7;
+
+        let heatmap = Canvas::new(Heatmap {
+            start,
+            counts,
+            goal: self.preferences.daily_goal,
+            color: styling::chart_color(),
+        })
+        .width(Length::Units(Heatmap::width(weeks) as u16))
+        .height(Length::Units(Heatmap::height() as u16));
+
+        let streaks = self.history.streaks(today);
+        let reviewed_today = per_day.get(&today).copied().unwrap_or(0);
+        let goal = self.preferences.daily_goal;
+        let days = |count: usize| match count {
+            1 => "1 day".to_owned(),
+            count => format!("{} days", count),
+        };
+
+        let mut goal_input = text_input("Goal", &self.daily_goal_input, Message::DailyGoalChanged)
+            .width(Length::Units(50))
+            .padding(3)
+            .size(15);
+        if self.daily_goal_input.trim().parse::<usize>().unwrap_or(0) == 0 {
+            goal_input = goal_input.style(styling::wrong_tex_input_style());
+        }
+
+        let summary = row![
+            text(format!("Today: {}/{}", reviewed_today, goal)).size(15),
+            progress_bar(0.0..=1.0, reviewed_today as f32 / goal.max(1) as f32)
+                .height(Length::Units(8))
+                .width(Length::Units(100)),
+            text(format!("Streak: {}", days(streaks.current))).size(15),
+            text(format!("Longest: {}", days(streaks.longest))).size(15),
+            horizontal_space(Length::Fill),
+            text("Daily goal").size(15),
+            goal_input,
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        column![heatmap, summary]
+            .spacing(5)
+            .width(Length::Units(Heatmap::width(weeks) as u16))
+            .into()
+    }
+
+    fn deck_card(&self, deck: &Deck) -> Element<Message> {
+        let card = column![text(deck.title.clone()), text(deck.description.clone())]
+            .padding(10)
+            .spacing(15);
+
+        let counts = self.due_counts(deck);
+        let mut badges = row![
+            text(format!("{} due / {} new", counts.due, counts.new)).size(14),
+            text(if deck.is_public { "Public" } else { "Private" }).size(14)
+        ]
+        .spacing(10);
+        if self.deck_cache.is_available_offline(&deck.id) {
+            badges = badges.push(text("Available offline").size(14));
+        }
+        if self.outbox.touches(&deck.id) {
+            badges = badges.push(text("Waiting to sync").size(14));
+        }
+
+        card.push(badges).into()
+    }
+
     fn deck_details_view(&self) -> Element<Message> {
         let mut details = (String::from(""), String::from(""));
         if self.already_selected {
@@ -892,7 +3319,11 @@ impl Shisho {
             deck_details_title_row = deck_details_title_row.push(
                 column![row![
                     button(text("Start").size(15)).on_press(Message::StartRound),
+                    button(text("Cram").size(15)).on_press(Message::StartCramRound),
                     button(text("Edit").size(15)).on_press(Message::EditDeck),
+                    button(text("Export").size(15)).on_press(Message::ShowExportDialog),
+                    button(text("Statistics").size(15)).on_press(Message::ShowStatistics),
+                    button(text("Leeches").size(15)).on_press(Message::ShowLeeches),
                     button(text("Delete").size(15))
                         .on_press(Message::DeleteDeck)
                         .style(iced::theme::Button::Destructive)
@@ -911,10 +3342,161 @@ impl Shisho {
                     .size(22)
                     .horizontal_alignment(Horizontal::Center),
                 text(details.1).size(22)
-            ])
-            .push(row![text("Cards: ")
-                .size(22)
-                .horizontal_alignment(Horizontal::Center),]);
+            ]);
+
+        if self.already_selected && !self.decks[self.selected_deck].folder.is_empty() {
+            deck_details_column = deck_details_column.push(row![
+                text("Folder: ").size(22),
+                text(folders::format_path(&self.decks[self.selected_deck].folder)).size(22)
+            ]);
+        }
+
+        if self.already_selected {
+            let settings = self
+                .preferences
+                .deck_settings(&self.decks[self.selected_deck].id);
+            deck_details_column = deck_details_column.push(
+                row![
+                    text("Scheduler: ").size(22),
+                    pick_list(
+                        &SchedulerKind::ALL[..],
+                        Some(settings.scheduler),
+                        Message::DeckSchedulerChanged
+                    ),
+                ]
+                .align_items(Alignment::Center),
+            );
+
+            let form = &self.deck_settings_form;
+            let mut new_cards_input = text_input(
+                "20",
+                &form.new_cards_per_day,
+                Message::NewCardsPerDayChanged,
+            )
+            .width(Length::Units(60))
+            .padding(5);
+            if form.new_cards_per_day.trim().parse::<usize>().is_err() {
+                new_cards_input = new_cards_input.style(styling::wrong_tex_input_style());
+            }
+            let mut reviews_input =
+                text_input("200", &form.reviews_per_day, Message::ReviewsPerDayChanged)
+                    .width(Length::Units(60))
+                    .padding(5);
+            if form.reviews_per_day.trim().parse::<usize>().is_err() {
+                reviews_input = reviews_input.style(styling::wrong_tex_input_style());
+            }
+            let mut steps_input = text_input(
+                "1m 10m 1d",
+                &form.learning_steps,
+                Message::LearningStepsChanged,
+            )
+            .padding(5);
+            if preferences::parse_learning_steps(&form.learning_steps).is_none() {
+                steps_input = steps_input.style(styling::wrong_tex_input_style());
+            }
+
+            deck_details_column = deck_details_column.push(
+                row![
+                    text("New cards a day:").size(15),
+                    new_cards_input,
+                    text("Reviews a day:").size(15),
+                    reviews_input,
+                    text("Learning steps:").size(15),
+                    steps_input,
+                ]
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+
+            let mut leech_input =
+                text_input("8", &form.leech_threshold, Message::LeechThresholdChanged)
+                    .width(Length::Units(60))
+                    .padding(5);
+            if form.leech_threshold.trim().parse::<u32>().is_err() {
+                leech_input = leech_input.style(styling::wrong_tex_input_style());
+            }
+            deck_details_column = deck_details_column.push(
+                row![
+                    text("Leech after").size(15),
+                    leech_input,
+                    text("lapses (0 for never), then").size(15),
+                    pick_list(
+                        &LeechAction::ALL[..],
+                        Some(settings.leech_action),
+                        Message::LeechActionChanged
+                    ),
+                ]
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+
+            let exam = &self.preferences.exam;
+            let mut exam_seconds_input =
+                text_input("600", &self.exam_seconds_input, Message::ExamSecondsChanged)
+                    .width(Length::Units(60))
+                    .padding(5);
+            if !matches!(self.exam_seconds_input.trim().parse::<u64>(), Ok(seconds) if seconds > 0)
+            {
+                exam_seconds_input = exam_seconds_input.style(styling::wrong_tex_input_style());
+            }
+            deck_details_column = deck_details_column.push(
+                row![
+                    text("Exam:").size(15),
+                    exam_seconds_input,
+                    pick_list(
+                        &ExamLimit::ALL[..],
+                        Some(exam.limit),
+                        Message::ExamLimitChanged
+                    ),
+                    checkbox(
+                        "Allow changing answers",
+                        exam.allow_changes,
+                        Message::ExamAnswerChangesToggled
+                    )
+                    .size(16),
+                    button(text("Start exam").size(15)).on_press(Message::StartExam),
+                ]
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        if self.already_selected {
+            let mut filter_button = button(text("Start with tags").size(15));
+            let mut filter_row = column![].spacing(5);
+            if !self.round_tag_filter.trim().is_empty() {
+                match TagFilter::parse(&self.round_tag_filter) {
+                    Ok(_) => filter_button = filter_button.on_press(Message::StartFilteredRound),
+                    Err(err) => filter_row = filter_row.push(text(err.to_string()).size(14)),
+                }
+            }
+            filter_row = filter_row.push(
+                row![
+                    text_input(
+                        "Tag filter, e.g. verbs AND NOT irregular",
+                        &self.round_tag_filter,
+                        Message::RoundTagFilterChanged,
+                    )
+                    .on_submit(Message::StartFilteredRound)
+                    .padding(5),
+                    filter_button
+                ]
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+            deck_details_column = deck_details_column.push(filter_row).push(
+                checkbox(
+                    "Flashcard mode: reveal answers instead of choosing",
+                    self.preferences.flashcard_mode,
+                    Message::FlashcardModeToggled,
+                )
+                .size(16),
+            );
+        }
+
+        deck_details_column = deck_details_column.push(row![text("Cards: ")
+            .size(22)
+            .horizontal_alignment(Horizontal::Center),]);
 
         if self.already_selected {
             let cards_column = self.decks[self.selected_deck]
@@ -924,7 +3506,7 @@ impl Shisho {
                 .fold(
                     column![horizontal_space(Length::Fill)].spacing(10),
                     |cards_column, (index, card)| {
-                        let card_column = column![
+                        let mut card_column = column![
                             horizontal_space(Length::Fill),
                             text(format!(
                                 "{}/{}",
@@ -932,11 +3514,29 @@ impl Shisho {
                                 self.decks[self.selected_deck].cards.len()
                             ))
                             .size(25),
-                            row![text(format!("Question: {}", card.title))],
+                            text("Question:"),
+                            markdown(&card.title, 20),
                         ]
                         .padding(Padding::new(5))
                         .spacing(10)
                         .width(iced::Length::Fill);
+                        if !card.tags.is_empty() {
+                            card_column = card_column
+                                .push(text(format!("Tags: {}", card.tags.join(", "))).size(14));
+                        }
+                        let suspended = self.history.is_suspended(&card.id);
+                        card_column = card_column.push(
+                            row![
+                                text(if suspended { "Suspended" } else { "" }).size(14),
+                                horizontal_space(Length::Fill),
+                                button(
+                                    text(if suspended { "Unsuspend" } else { "Suspend" }).size(14)
+                                )
+                                .on_press(Message::ToggleSuspended(card.id.clone()))
+                                .style(iced::theme::Button::Secondary),
+                            ]
+                            .align_items(Alignment::Center),
+                        );
 
                         cards_column.push(container(card_column).style(styling::card_style()))
                     },
@@ -956,6 +3556,265 @@ impl Shisho {
             .spacing(25)
             .align_items(Alignment::Center);
 
+        let view_content = container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x();
+
+        Modal::new(self.show_export_file_dialog, view_content, || {
+            iced_aw::native::Card::new(
+                text("Export deck"),
+                column![
+                    text("Write path to the JSON file to create below:"),
+                    text_input(
+                        "Export file path",
+                        &self.export_file_path,
+                        Message::ExportFilePathChanged
+                    )
+                    .on_submit(Message::ExportDeck)
+                ],
+            )
+            .foot(
+                row![]
+                    .spacing(10)
+                    .padding(5)
+                    .width(Length::Fill)
+                    .push(
+                        button(text("Cancel").horizontal_alignment(Horizontal::Center))
+                            .width(Length::Fill)
+                            .on_press(Message::HideExportDialog),
+                    )
+                    .push(
+                        button(text("Export").horizontal_alignment(Horizontal::Center))
+                            .width(Length::Fill)
+                            .on_press(Message::ExportDeck),
+                    ),
+            )
+            .max_width(500)
+            .on_close(Message::HideExportDialog)
+            .into()
+        })
+        .backdrop(Message::HideExportDialog)
+        .on_esc(Message::HideExportDialog)
+        .into()
+    }
+
+    fn statistics_view(&self) -> Element<Message> {
+        let deck = &self.decks[self.selected_deck];
+        let statistics = self.history.deck_statistics(deck, chrono::Utc::now());
+
+        let title_row = row![
+            column![
+                button(text("Back").size(15)).on_press(Message::CancelRound(TargetView::Details))
+            ]
+            .align_items(Alignment::Start)
+            .width(Length::Fill),
+            column![text("Statistics").size(30)]
+                .align_items(Alignment::Center)
+                .width(Length::Fill),
+            horizontal_space(Length::Fill),
+        ];
+
+        let accuracy: Element<Message> = match (
+            statistics.accuracy_by_day.first(),
+            statistics.accuracy_by_day.last(),
+        ) {
+            (Some(first), Some(last)) => Canvas::new(LineChart {
+                values: statistics
+                    .accuracy_by_day
+                    .iter()
+                    .map(|(_, accuracy)| *accuracy)
+                    .collect(),
+                max: 100.0,
+                color: styling::chart_color(),
+                first_label: first.0.format("%b %d").to_string(),
+                last_label: last.0.format("%b %d").to_string(),
+            })
+            .width(Length::Units(500))
+            .height(Length::Units(160))
+            .into(),
+            _ => text("This deck has not been studied yet").size(15).into(),
+        };
+
+        let maturity = Canvas::new(BarChart {
+            bars: vec![
+                ("New".to_owned(), statistics.new_cards as f32),
+                ("Learning".to_owned(), statistics.learning_cards as f32),
+                ("Mature".to_owned(), statistics.mature_cards as f32),
+            ],
+            color: styling::chart_color(),
+            label_every: 1,
+        })
+        .width(Length::Units(500))
+        .height(Length::Units(140));
+
+        let forecast = Canvas::new(BarChart {
+            bars: statistics
+                .forecast
+                .iter()
+                .enumerate()
+                .map(|(day, count)| {
+                    let label = match day {
+                        0 => "Today".to_owned(),
+                        day => format!("+{}", day),
+                    };
+                    (label, *count as f32)
+                })
+                .collect(),
+            color: styling::chart_color(),
+            label_every: 7,
+        })
+        .width(Length::Units(500))
+        .height(Length::Units(160));
+
+        let average_response = match statistics.average_response_ms {
+            Some(ms) => format!("Average response time: {:.1}s", ms as f32 / 1000.0),
+            None => "Average response time: not measured yet".to_owned(),
+        };
+
+        let mut hardest = column![text("Hardest cards").size(22)].spacing(5);
+        if statistics.hardest_cards.is_empty() {
+            hardest = hardest.push(text("No card has been forgotten yet").size(15));
+        }
+        for (title, lapses) in statistics.hardest_cards.iter().take(10) {
+            hardest = hardest.push(
+                row![
+                    text(title).size(15).width(Length::Fill),
+                    text(match lapses {
+                        1 => "1 lapse".to_owned(),
+                        lapses => format!("{} lapses", lapses),
+                    })
+                    .size(15)
+                ]
+                .spacing(10),
+            );
+        }
+
+        let statistics_column = column![
+            title_row,
+            text(&deck.title).size(22),
+            text("Accuracy over time").size(22),
+            accuracy,
+            text("Cards by maturity").size(22),
+            maturity,
+            text(format!("Reviews due in the next {} days", FORECAST_DAYS)).size(22),
+            forecast,
+            text(average_response).size(15),
+            text(format!("Answers undone: {}", statistics.undone_answers)).size(15),
+            container(hardest)
+                .padding(10)
+                .width(Length::Fill)
+                .style(styling::card_style()),
+        ]
+        .spacing(15)
+        .max_width(500);
+
+        let body = container(
+            scrollable(row![statistics_column].padding(Padding::from([0, 20])))
+                .scrollbar_width(5)
+                .scroller_width(5),
+        );
+        let content = column![shisho_text(), body]
+            .spacing(25)
+            .align_items(Alignment::Center);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
+
+    fn leeches_view(&self) -> Element<Message> {
+        let deck = &self.decks[self.selected_deck];
+        let settings = self.preferences.deck_settings(&deck.id);
+        let leeches = self.history.leeches(deck, &settings);
+
+        let title_row = row![
+            column![
+                button(text("Back").size(15)).on_press(Message::CancelRound(TargetView::Details))
+            ]
+            .align_items(Alignment::Start)
+            .width(Length::Fill),
+            column![text("Leeches").size(30)]
+                .align_items(Alignment::Center)
+                .width(Length::Fill),
+            column![button(text("Edit deck").size(15)).on_press(Message::EditDeck)]
+                .align_items(Alignment::End)
+                .width(Length::Fill),
+        ];
+
+        let mut leeches_column = column![
+            title_row,
+            text(&deck.title).size(22),
+            text(match settings.leech_threshold {
+                0 => "Leech detection is off for this deck".to_owned(),
+                threshold => format!("Cards forgotten {} times or more", threshold),
+            })
+            .size(15),
+        ]
+        .spacing(15)
+        .max_width(500);
+
+        if leeches.is_empty() && settings.leech_threshold > 0 {
+            leeches_column = leeches_column.push(text("No leeches, well done"));
+        }
+        let owned = self
+            .profile
+            .as_ref()
+            .map_or(false, |profile| deck.owner_id == profile.id);
+        let untagged = leeches.iter().any(|(card, _)| {
+            !card
+                .tags
+                .iter()
+                .any(|tag| tag.eq_ignore_ascii_case(LEECH_TAG))
+        });
+        if owned && untagged {
+            leeches_column = leeches_column.push(
+                row![
+                    text("Tagging uploads the deck again, with new card ids").size(14),
+                    button(text("Tag them").size(14)).on_press(Message::TagLeeches),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+        for (card, lapses) in leeches {
+            let suspended = self.history.is_suspended(&card.id);
+            let mut card_column = column![
+                text("Question:"),
+                markdown(&card.title, 20),
+                text(format!("Lapses: {}", lapses)).size(15),
+            ]
+            .padding(Padding::new(5))
+            .spacing(10)
+            .width(Length::Fill);
+            if !card.explanation.is_empty() {
+                card_column = card_column
+                    .push(text("Explanation:").size(15))
+                    .push(markdown(&card.explanation, 15));
+            }
+            card_column = card_column.push(
+                row![
+                    button(text("Edit").size(14)).on_press(Message::EditDeck),
+                    button(text(if suspended { "Unsuspend" } else { "Suspend" }).size(14))
+                        .on_press(Message::ToggleSuspended(card.id.clone())),
+                ]
+                .spacing(10),
+            );
+            leeches_column =
+                leeches_column.push(container(card_column).style(styling::card_style()));
+        }
+
+        let body = container(
+            scrollable(row![leeches_column].padding(Padding::from([0, 20])))
+                .scrollbar_width(5)
+                .scroller_width(5),
+        );
+        let content = column![shisho_text(), body]
+            .spacing(25)
+            .align_items(Alignment::Center);
+
         container(content)
             .width(Length::Fill)
             .height(Length::Fill)
@@ -966,17 +3825,17 @@ impl Shisho {
     fn round_view(&self) -> Element<Message> {
         let title_row = row![
             text("Deck:").size(22),
-            text(self.decks[self.selected_deck].title.clone()).size(22)
+            text(self.round_title.clone()).size(22)
         ];
 
         let round_info_row = row![text(format!(
             "{}/{} Cards",
             self.selected_card + 1,
-            self.decks[self.selected_deck].cards.len()
+            self.round_cards.len()
         ))
         .size(35)];
 
-        let answers_column = self.decks[self.selected_deck].cards[self.selected_card]
+        let answers_column = self.round_cards[self.selected_card]
             .answers
             .iter()
             .enumerate()
@@ -988,13 +3847,7 @@ impl Shisho {
                         is_selected = Some(index);
                     }
 
-                    let mut row = row![radio(
-                        format!("{}) {}", index + 1, answer.text),
-                        index,
-                        is_selected,
-                        Message::Answer,
-                    )]
-                    .spacing(10);
+                    let mut row = answer_radio(index, &answer.text, is_selected, Message::Answer);
 
                     if self.check {
                         if answer.is_correct && is_selected != None {
@@ -1007,41 +3860,114 @@ impl Shisho {
                 },
             );
 
-        let card = column![
-            row![
-                text(format!(
-                    "Question: {}",
-                    self.decks[self.selected_deck].cards[self.selected_card].title
-                )),
-                horizontal_space(iced::Length::Units(100))
-            ],
-            row![text("Possible answers:")],
-            answers_column
-        ]
-        .padding(Padding::new(5))
-        .spacing(10)
-        .max_width(400);
+        let round_card = &self.round_cards[self.selected_card];
+        let mut card = column![text("Question:"), markdown(&round_card.title, 20)]
+            .padding(Padding::new(5))
+            .spacing(10)
+            .max_width(400);
+
+        if !self.flashcards {
+            card = card
+                .push(row![text("Possible answers:")])
+                .push(answers_column);
+        } else if self.check {
+            card = card.push(text("Answer:"));
+            for answer in round_card.answers.iter().filter(|a| a.is_correct) {
+                card = card.push(if markdown::has_math(&answer.text) {
+                    Element::from(row![text("-"), markdown(&answer.text, 20)].spacing(6))
+                } else {
+                    text(format!("- {}", answer.text)).into()
+                });
+            }
+        } else {
+            card = card.push(
+                button(text("Show answer (Space)"))
+                    .on_press(Message::ShowAnswer)
+                    .width(Length::Fill),
+            );
+        }
+        if self.check && !round_card.explanation.is_empty() {
+            card = card
+                .push(text("Explanation:").size(16))
+                .push(markdown(&round_card.explanation, 16));
+        }
 
         let card_container = container(card).style(styling::card_style());
 
-        let back_to_decks_button =
-            button(text("Back to deck")).on_press(Message::CancelRound(TargetView::Details));
+        let mut round_buttons = row![self.round_back_button()].spacing(10);
+        if self.exam.is_none() {
+            round_buttons = round_buttons.push(button(text("Pause")).on_press(Message::PauseRound));
+            let mut undo_button = button(text("Undo (Ctrl+Z)"));
+            if self.answered || self.selected_card > 0 {
+                undo_button = undo_button.on_press(Message::UndoAnswer);
+            }
+            round_buttons = round_buttons.push(undo_button);
+        }
 
-        let mut content = column![
-            title_row,
-            back_to_decks_button,
-            round_info_row,
-            card_container
-        ]
-        .align_items(Alignment::Center)
-        .spacing(15);
+        let mut content = column![title_row, round_buttons, round_info_row, card_container]
+            .align_items(Alignment::Center)
+            .spacing(15);
+
+        if let Some(exam) = &self.exam {
+            let time_left = exam.time_left(Instant::now());
+            let countdown = match exam.settings.limit {
+                ExamLimit::Overall => format!("Time left: {}", format_clock(time_left)),
+                ExamLimit::PerCard => {
+                    format!("Time left for this card: {}", format_clock(time_left))
+                }
+            };
+            let mut previous_button = button(text("Previous"));
+            if exam.can_go_back() && self.selected_card > 0 {
+                previous_button = previous_button.on_press(Message::PreviousExamCard);
+            }
+            let mut next_button = button(text("Next (Enter)"));
+            if self.selected_card + 1 < self.round_cards.len() {
+                next_button = next_button.on_press(Message::NextExamCard);
+            }
+            content = content.push(text(countdown).size(25)).push(
+                row![
+                    previous_button,
+                    next_button,
+                    button(text("Submit exam")).on_press(Message::SubmitExam)
+                ]
+                .spacing(10),
+            );
+        }
 
         if self.check {
-            let progress = self.duration.as_secs_f32() / ANSWER_DELAY.as_secs_f32();
-            let progress_bar = progress_bar(0.0..=1.0, progress)
-                .height(Length::Units(5))
-                .width(Length::Units(600));
-            content = content.push(progress_bar);
+            let rating = self.round_ratings[self.selected_card];
+            let ratings = Rating::ALL.iter().enumerate().fold(
+                row![].spacing(10),
+                |ratings, (index, option)| {
+                    let mut rating_button = button(text(format!("{} {}", index + 1, option)));
+                    if rating.is_none() {
+                        rating_button = rating_button.on_press(Message::Rate(*option));
+                    } else if rating != Some(*option) {
+                        rating_button = rating_button.style(iced::theme::Button::Secondary);
+                    }
+                    ratings.push(rating_button)
+                },
+            );
+            content = content
+                .push(text("How well did you remember it?"))
+                .push(ratings);
+        }
+
+        if self.check && self.round_ratings[self.selected_card].is_some() {
+            if self.preferences.manual_advance {
+                content = content.push(button(text("Next (Enter)")).on_press(Message::Continue));
+            } else {
+                let delay = self.answer_delay().as_secs_f32();
+                let progress = if delay > 0.0 {
+                    self.duration.as_secs_f32() / delay
+                } else {
+                    1.0
+                };
+                let progress_bar = progress_bar(0.0..=1.0, progress)
+                    .height(Length::Units(5))
+                    .width(Length::Units(600));
+                content = content.push(progress_bar);
+            }
         }
 
         container(content)
@@ -1051,10 +3977,18 @@ impl Shisho {
             .into()
     }
 
+    fn round_back_button(&self) -> iced::widget::Button<Message> {
+        match self.round_return {
+            TargetView::Details => button(text("Back to deck")),
+            TargetView::Welcome => button(text("Back to decks")),
+        }
+        .on_press(Message::CancelRound(self.round_return.clone()))
+    }
+
     fn results_view(&self) -> Element<Message> {
         let title_row = row![
             text("Deck:").size(22),
-            text(self.decks[self.selected_deck].title.clone()).size(22)
+            text(self.round_title.clone()).size(22)
         ];
 
         let score = self.score * self.duration.as_secs_f32() / RESULTS_DELAY.as_secs_f32();
@@ -1065,86 +3999,108 @@ impl Shisho {
                 .style(iced::theme::Text::Color(styling::score_text_color(score)))
         ];
 
-        let cards_column = self.decks[self.selected_deck]
-            .cards
-            .iter()
-            .enumerate()
-            .fold(
-                column![]
-                    .padding(Padding::from([0, 12, 0, 0]))
-                    .spacing(10)
-                    .align_items(Alignment::Center)
-                    .max_width(400),
-                |cards_column, (card_index, card)| {
-                    let correct_card =
-                        card_is_correct(&card.answers, &self.selected_answers[card_index]);
-                    let answers_column = card.answers.iter().enumerate().fold(
-                        column![].width(iced::Length::Units(400)),
-                        |answers_column, (answer_index, answer)| {
-                            let mut is_selected = None;
-                            if self.selected_answers[card_index][answer_index] {
-                                is_selected = Some(answer_index);
-                            }
+        let cards_column = self.round_cards.iter().enumerate().fold(
+            column![]
+                .padding(Padding::from([0, 12, 0, 0]))
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .max_width(400),
+            |cards_column, (card_index, card)| {
+                let correct_card = self.round_card_correct(card_index);
+                let answers_column = card.answers.iter().enumerate().fold(
+                    column![].width(iced::Length::Units(400)),
+                    |answers_column, (answer_index, answer)| {
+                        let mut is_selected = None;
+                        if self.selected_answers[card_index][answer_index] {
+                            is_selected = Some(answer_index);
+                        }
 
-                            let mut row = row![radio(
-                                format!("{}) {}", answer_index + 1, answer.text),
-                                answer_index,
-                                is_selected,
-                                Message::None,
-                            )]
-                            .spacing(10);
-
-                            if self.check {
-                                if answer.is_correct {
-                                    row = row.push(right_icon().vertical_alignment(Vertical::Top));
-                                } else if !answer.is_correct
-                                    && !correct_card
-                                    && self.selected_answers[card_index][answer_index]
-                                {
-                                    row = row.push(wrong_icon().vertical_alignment(Vertical::Top));
-                                }
+                        let mut row =
+                            answer_radio(answer_index, &answer.text, is_selected, Message::None);
+
+                        if self.check {
+                            if answer.is_correct {
+                                row = row.push(right_icon().vertical_alignment(Vertical::Top));
+                            } else if !answer.is_correct
+                                && !correct_card
+                                && self.selected_answers[card_index][answer_index]
+                            {
+                                row = row.push(wrong_icon().vertical_alignment(Vertical::Top));
                             }
-                            answers_column.push(row)
-                        },
-                    );
+                        }
+                        answers_column.push(row)
+                    },
+                );
 
-                    let card_widget = column![
-                        row![text(format!(
-                            "Question: {}",
-                            self.decks[self.selected_deck].cards[card_index].title
-                        ))],
-                        row![text("Possible answers:")],
-                        answers_column
-                    ]
-                    .padding(Padding::new(5))
-                    .spacing(10);
+                let mut card_widget = column![
+                    text("Question:"),
+                    markdown(&card.title, 20),
+                    row![text("Possible answers:")],
+                    answers_column
+                ]
+                .padding(Padding::new(5))
+                .spacing(10);
+                if let Some(rating) = self.round_ratings[card_index] {
+                    card_widget = card_widget.push(text(format!("Rated: {}", rating)).size(15));
+                }
 
-                    let mut card_container = container(card_widget);
+                let mut card_container = container(card_widget);
 
-                    if correct_card {
-                        card_container = card_container.style(styling::correct_card_style());
-                    } else {
-                        card_container = card_container.style(styling::wrong_card_style());
-                    }
-                    cards_column.push(card_container)
-                },
-            );
+                if correct_card {
+                    card_container = card_container.style(styling::correct_card_style());
+                } else {
+                    card_container = card_container.style(styling::wrong_card_style());
+                }
+                cards_column.push(card_container)
+            },
+        );
 
         let cards_scroll = scrollable(cards_column)
             .scrollbar_width(5)
             .scroller_width(5);
 
-        let back_to_decks_button =
-            button("Back to deck").on_press(Message::CancelRound(TargetView::Details));
+        let back_to_decks_button = self.round_back_button();
 
-        let content = column![
-            title_row,
-            back_to_decks_button,
-            round_info_row,
-            cards_scroll
-        ]
-        .align_items(Alignment::Center)
-        .spacing(15);
+        let mut content = column![title_row, back_to_decks_button, round_info_row]
+            .align_items(Alignment::Center)
+            .spacing(15);
+
+        if let Some(exam) = &self.exam {
+            if let Some(time_taken) = exam.time_taken {
+                content = content.push(
+                    text(format!(
+                        "Time taken: {} ({})",
+                        format_clock(time_taken),
+                        exam.describe_limit()
+                    ))
+                    .size(22),
+                );
+            }
+            let attempts = self.history.exam_attempts(&self.round_decks[0]);
+            if attempts.len() > 1 {
+                let attempts_column = attempts.iter().skip(1).take(5).fold(
+                    column![text("Earlier attempts:").size(18)].spacing(2),
+                    |attempts_column, attempt| {
+                        attempts_column.push(
+                            text(format!(
+                                "{}: {:.2}% in {} ({})",
+                                attempt
+                                    .finished_at
+                                    .with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M"),
+                                attempt.score(),
+                                format_clock(Duration::from_millis(attempt.time_taken_ms)),
+                                attempt.time_limit
+                            ))
+                            .size(15),
+                        )
+                    },
+                );
+                content = content.push(attempts_column);
+            }
+        }
+
+        content = content.push(cards_scroll);
 
         container(content)
             .width(Length::Fill)
@@ -1172,6 +4128,42 @@ impl Shisho {
             )
         ];
 
+        let folder_row = row![
+            text("Folder: ").size(22),
+            text_input(
+                "Folder, e.g. Languages::Greek (optional)",
+                &self.edit_deck.folder,
+                Message::EditDeckFolderChanged,
+            )
+        ];
+
+        let tags_row = row![
+            text("Tags: ").size(22),
+            text_input(
+                "Comma separated tags",
+                &self.edit_deck.tags.join(","),
+                Message::EditDeckTagsChanged,
+            )
+        ];
+
+        let visibility_row = row![
+            text("Visibility: ").size(22),
+            radio(
+                "Private",
+                false,
+                Some(self.edit_deck.is_public),
+                Message::EditDeckVisibilityChanged
+            ),
+            radio(
+                "Public",
+                true,
+                Some(self.edit_deck.is_public),
+                Message::EditDeckVisibilityChanged
+            )
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
         let cards = self.edit_deck.cards.iter().enumerate().fold(
             column![].padding(Padding::from([0, 12, 0, 12])).spacing(10),
             |cards, (card_index, card)| {
@@ -1207,20 +4199,56 @@ impl Shisho {
                         .spacing(10)
                         .padding(Padding::from([0, 0, 10, 0]));
 
-                        answers.push(text_row).push(is_correct_row).into()
+                        let mut answers = answers.push(text_row);
+                        // Math is hard to check as source, so it is always previewed.
+                        if self.edit_preview || markdown::has_math(&answer.text) {
+                            answers = answers.push(markdown(&answer.text, 20));
+                        }
+                        answers.push(is_correct_row).into()
                     },
                 );
-                let card_widget = column![
+                let mut card_widget = column![row![
+                    text("Question: "),
+                    text_input("Card's question", &card.title, move |title| {
+                        Message::CardTitleChanged(card_index, title)
+                    },)
+                ]
+                .spacing(10)]
+                .padding(Padding::from([7, 7]))
+                .spacing(10);
+                if self.edit_preview || markdown::has_math(&card.title) {
+                    card_widget = card_widget.push(markdown(&card.title, 20));
+                }
+                card_widget = card_widget.push(
                     row![
-                        text("Question: "),
-                        text_input("Card's question", &card.title, move |title| {
-                            Message::CardTitleChanged(card_index, title)
-                        },)
+                        text("Explanation: "),
+                        text_input(
+                            "Shown once answered (optional)",
+                            &card.explanation,
+                            move |explanation| {
+                                Message::CardExplanationChanged(card_index, explanation)
+                            }
+                        )
                     ]
                     .spacing(10),
-                    row![text("Answers:")],
-                    answers,
-                    row![
+                );
+                if (self.edit_preview || markdown::has_math(&card.explanation))
+                    && !card.explanation.is_empty()
+                {
+                    card_widget = card_widget.push(markdown(&card.explanation, 16));
+                }
+                let card_widget = card_widget.push(
+                    column![
+                        row![
+                            text("Tags: "),
+                            text_input("Comma separated tags", &card.tags.join(","), move |tags| {
+                                Message::CardTagsChanged(card_index, tags)
+                            })
+                        ]
+                        .spacing(10),
+                        row![text("Answers:")],
+                        answers,
+                        row![
                             column![].width(iced::Length::Fill),
                             column![
                                 button(text("Add answer")).on_press(Message::AddAnswer(card_index))
@@ -1233,9 +4261,9 @@ impl Shisho {
                             .width(iced::Length::Fill)
                             .align_items(Alignment::End)
                         ]
-                ]
-                .padding(Padding::from([7, 7]))
-                .spacing(10);
+                    ]
+                    .spacing(10),
+                );
 
                 cards.push(container(card_widget).style(styling::card_style()))
             },
@@ -1269,10 +4297,26 @@ impl Shisho {
             }
             _ => {}
         }
+        button_row = button_row.push(
+            checkbox(
+                "Preview Markdown",
+                self.edit_preview,
+                Message::EditPreviewToggled,
+            )
+            .size(16),
+        );
 
-        let content = column![title_row, description_row, button_row, cards_scroll]
-            .align_items(Alignment::Center)
-            .spacing(15);
+        let content = column![
+            title_row,
+            description_row,
+            folder_row,
+            tags_row,
+            visibility_row,
+            button_row,
+            cards_scroll
+        ]
+        .align_items(Alignment::Center)
+        .spacing(15);
 
         let view_content = container(content)
             .width(Length::Fill)
@@ -1316,6 +4360,383 @@ impl Shisho {
         .on_esc(Message::HideDialog)
         .into()
     }
+
+    /// Asks how to settle a queued change that clashes with the server.
+    fn conflict_dialog<'a>(&'a self, content: Element<'a, Message>) -> Element<'a, Message> {
+        Modal::new(self.conflict.is_some(), content, || {
+            let (message, choices) = match &self.conflict {
+                Some(Conflict::DeletedOnServer { mutation }) => (
+                    format!(
+                        "\"{}\" was deleted on another device while you were editing it.",
+                        mutation.title()
+                    ),
+                    vec![
+                        ("Discard my changes", Resolution::KeepTheirs),
+                        ("Upload mine as a new deck", Resolution::KeepMine),
+                    ],
+                ),
+                Some(Conflict::EditedElsewhere {
+                    mutation: Mutation::Delete { base },
+                    ..
+                }) => (
+                    format!(
+                        "\"{}\" was changed on another device after you deleted it.",
+                        base.title
+                    ),
+                    vec![
+                        ("Keep their version", Resolution::KeepTheirs),
+                        ("Delete anyway", Resolution::KeepMine),
+                    ],
+                ),
+                Some(Conflict::EditedElsewhere { mutation, .. }) => (
+                    format!(
+                        "\"{}\" was also changed on another device.",
+                        mutation.title()
+                    ),
+                    vec![
+                        ("Keep theirs", Resolution::KeepTheirs),
+                        ("Keep both", Resolution::KeepBoth),
+                        ("Keep mine", Resolution::KeepMine),
+                    ],
+                ),
+                None => (String::new(), Vec::new()),
+            };
+
+            let buttons = choices.into_iter().fold(
+                row![].spacing(10).padding(5).width(Length::Fill),
+                |buttons, (label, resolution)| {
+                    buttons.push(
+                        button(text(label).horizontal_alignment(Horizontal::Center))
+                            .width(Length::Fill)
+                            .on_press(Message::ResolveConflict(resolution)),
+                    )
+                },
+            );
+
+            iced_aw::native::Card::new(text("Sync conflict"), text(message))
+                .foot(buttons)
+                .max_width(500)
+                .into()
+        })
+        .into()
+    }
+
+    fn discover_view(&self) -> Element<Message> {
+        let back_button =
+            button(text("Back").size(15)).on_press(Message::CancelRound(TargetView::Welcome));
+
+        let title_row = row![
+            column![back_button]
+                .align_items(Alignment::Start)
+                .width(iced::Length::Fill),
+            column![text("Discover").size(30)]
+                .align_items(Alignment::Center)
+                .width(iced::Length::Fill),
+            column![].width(iced::Length::Fill),
+        ]
+        .width(iced::Length::Units(500));
+
+        let search_row = row![
+            text_input(
+                "Search public decks",
+                &self.public_deck_search,
+                Message::PublicDeckSearchChanged
+            )
+            .on_submit(Message::SearchPublicDecks)
+            .padding(5),
+            button("Search").on_press(Message::SearchPublicDecks)
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center)
+        .width(iced::Length::Units(500));
+
+        // The user's own public decks are already in their library.
+        let own_deck = |deck: &Deck| {
+            self.profile
+                .as_ref()
+                .map_or(false, |profile| deck.owner_id == profile.id)
+        };
+        let decks_column = self
+            .public_decks
+            .iter()
+            .enumerate()
+            .filter(|(_, deck)| !own_deck(deck))
+            .fold(
+                column![].spacing(15).width(iced::Length::Units(400)),
+                |decks_column, (index, deck)| {
+                    let deck_column = column![
+                        text(deck.title.clone()),
+                        text(deck.description.clone()),
+                        row![
+                            text(format!("by {}", deck.author)).size(14),
+                            text(format!("{} cards", deck.card_count)).size(14)
+                        ]
+                        .spacing(10)
+                    ]
+                    .padding(10)
+                    .spacing(10);
+
+                    decks_column.push(
+                        button(
+                            container(deck_column)
+                                .style(styling::card_style())
+                                .width(iced::Length::Units(400)),
+                        )
+                        .on_press(Message::PreviewPublicDeck(index))
+                        .style(styling::invisible_button()),
+                    )
+                },
+            );
+
+        let mut body = row![scrollable(decks_column)
+            .scrollbar_width(5)
+            .scroller_width(5)]
+        .spacing(30);
+
+        if let Some(deck) = &self.public_deck_preview {
+            let cards_column = deck.cards.iter().enumerate().fold(
+                column![].spacing(10),
+                |cards_column, (index, card)| {
+                    let card_column = column![
+                        text(format!("{}/{}", index + 1, deck.cards.len())).size(18),
+                        text("Question:"),
+                        markdown(&card.title, 20),
+                    ]
+                    .padding(Padding::new(5))
+                    .spacing(5)
+                    .width(iced::Length::Fill);
+
+                    cards_column.push(container(card_column).style(styling::card_style()))
+                },
+            );
+
+            let preview = column![
+                row![
+                    button(text("Clone to my library").size(15)).on_press(Message::ClonePublicDeck),
+                    button(text("Close").size(15)).on_press(Message::ClosePublicDeckPreview)
+                ]
+                .spacing(5),
+                text(deck.title.clone()).size(26),
+                text(format!("by {}", deck.author)).size(14),
+                text(deck.description.clone()),
+                scrollable(cards_column)
+                    .scrollbar_width(5)
+                    .scroller_width(5)
+            ]
+            .spacing(10)
+            .width(iced::Length::Units(400));
+
+            body = body.push(preview);
+        }
+
+        let content = column![shisho_text(), title_row, search_row, body]
+            .spacing(25)
+            .align_items(Alignment::Center);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
+
+    fn profile_view(&self) -> Element<Message> {
+        let back_button =
+            button(text("Back").size(15)).on_press(Message::CancelRound(TargetView::Welcome));
+
+        let title_row = row![
+            column![back_button]
+                .align_items(Alignment::Start)
+                .width(iced::Length::Fill),
+            column![text("Profile").size(30)]
+                .align_items(Alignment::Center)
+                .width(iced::Length::Fill),
+            column![button(text("Save").size(15)).on_press(Message::SendUpdateProfile)]
+                .align_items(Alignment::End)
+                .width(iced::Length::Fill),
+        ]
+        .width(iced::Length::Units(500));
+
+        let username = match &self.profile {
+            Some(profile) => profile.username.clone(),
+            None => self.login.username.clone(),
+        };
+
+        let profile_column = column![
+            row![text("Username: ").size(22), text(username).size(22)],
+            row![
+                text("Display name: ").size(22),
+                text_input(
+                    "Display name",
+                    &self.profile_display_name,
+                    Message::ProfileDisplayNameChanged,
+                )
+                .on_submit(Message::SendUpdateProfile)
+            ]
+            .align_items(Alignment::Center),
+            row![
+                text("Bio: ").size(22),
+                text_input("Bio", &self.profile_bio, Message::ProfileBioChanged)
+                    .on_submit(Message::SendUpdateProfile)
+            ]
+            .align_items(Alignment::Center),
+        ]
+        .spacing(15)
+        .width(iced::Length::Units(500));
+
+        let statistics = self.history.statistics();
+        let last_studied = match statistics.last_studied {
+            Some(date) => date
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            None => "Never".to_owned(),
+        };
+
+        let statistics_column = column![
+            text("Study statistics").size(25),
+            text(format!("Rounds completed: {}", statistics.rounds)),
+            text(format!("Decks studied: {}", statistics.decks_studied)),
+            text(format!("Cards studied: {}", statistics.cards_studied)),
+            text(format!(
+                "Answers: {} ({:.2}% correct)",
+                statistics.reviews,
+                statistics.accuracy()
+            )),
+            text(format!("Average score: {:.2}%", statistics.average_score)),
+            text(format!("Last studied: {}", last_studied)),
+            button(text("Schedulers").size(15)).on_press(Message::ShowScheduling),
+        ]
+        .padding(Padding::new(10))
+        .spacing(10)
+        .width(iced::Length::Units(500));
+
+        let mut delay_input =
+            text_input("1", &self.answer_delay_input, Message::AnswerDelayChanged)
+                .width(Length::Units(60))
+                .padding(5);
+        if preferences::parse_answer_delay(&self.answer_delay_input).is_none() {
+            delay_input = delay_input.style(styling::wrong_tex_input_style());
+        }
+        let rounds_column = column![
+            text("Rounds").size(25),
+            row![
+                text("Next card after"),
+                delay_input,
+                text("seconds of feedback")
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            checkbox(
+                "Wait for Enter or Space instead",
+                self.preferences.manual_advance,
+                Message::ManualAdvanceToggled,
+            ),
+            checkbox(
+                "Count the score up on the results",
+                self.preferences.animate_score,
+                Message::ScoreAnimationToggled,
+            ),
+        ]
+        .padding(Padding::new(10))
+        .spacing(10)
+        .width(iced::Length::Units(500));
+
+        let content = column![
+            shisho_text(),
+            title_row,
+            profile_column,
+            container(statistics_column).style(styling::card_style()),
+            container(rounds_column).style(styling::card_style()),
+        ]
+        .spacing(25)
+        .align_items(Alignment::Center);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
+
+    fn scheduling_view(&self) -> Element<Message> {
+        let title_row = row![
+            column![button(text("Back").size(15)).on_press(Message::ShowProfile)]
+                .align_items(Alignment::Start)
+                .width(Length::Fill),
+            column![text("Schedulers").size(30)]
+                .align_items(Alignment::Center)
+                .width(Length::Fill),
+            horizontal_space(Length::Fill),
+        ]
+        .width(Length::Units(500));
+
+        let mut fit_button = button(text("Fit to my reviews").size(15));
+        let mut simulate_button = button(text("Simulate").size(15));
+        if !self.scheduling_busy {
+            fit_button = fit_button.on_press(Message::FitFsrs);
+            simulate_button = simulate_button.on_press(Message::SimulateSchedulers);
+        }
+
+        let mut fsrs_buttons = row![fit_button].spacing(10);
+        if self.preferences.fsrs.is_some() {
+            fsrs_buttons = fsrs_buttons
+                .push(button(text("Reset to defaults").size(15)).on_press(Message::ResetFsrs));
+        }
+        let fsrs_column = column![
+            text("FSRS").size(25),
+            text(match self.preferences.fsrs {
+                Some(_) => "Using weights fitted to your reviews",
+                None => "Using the default weights",
+            }),
+            fsrs_buttons,
+        ]
+        .padding(Padding::new(10))
+        .spacing(10)
+        .width(Length::Units(500));
+
+        let mut simulation_column = column![
+            text("Simulation").size(25),
+            text(format!(
+                "Replays your review history under each scheduler, then studies every due card for {} days.",
+                SIMULATION_DAYS
+            ))
+            .size(15),
+            simulate_button,
+        ]
+        .padding(Padding::new(10))
+        .spacing(10)
+        .width(Length::Units(500));
+        if !self.simulations.is_empty() {
+            simulation_column = simulation_column.push(row![
+                text("Scheduler").width(Length::Fill),
+                text("Reviews a day").width(Length::Fill),
+                text("Predicted retention").width(Length::Fill),
+            ]);
+        }
+        for simulation in self.simulations.iter() {
+            simulation_column = simulation_column.push(row![
+                text(simulation.scheduler.to_string()).width(Length::Fill),
+                text(format!("{:.1}", simulation.reviews_per_day)).width(Length::Fill),
+                text(format!("{:.1}%", simulation.retention * 100.0)).width(Length::Fill),
+            ]);
+        }
+
+        let content = column![
+            shisho_text(),
+            title_row,
+            container(fsrs_column).style(styling::card_style()),
+            container(simulation_column).style(styling::card_style()),
+        ]
+        .spacing(25)
+        .align_items(Alignment::Center);
+
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
 }
 
 fn shisho_text() -> iced::widget::Text<'static> {
@@ -1343,14 +4764,135 @@ fn refresh_icon() -> iced::widget::Text<'static> {
     icon('\u{E5D5}').size(20)
 }
 
-fn calculate_score(cards: &Vec<Card>, chosen: &Vec<Vec<bool>>) -> f32 {
-    let mut correct_cards = 0;
-    for (index, card) in cards.iter().enumerate() {
-        if card_is_correct(&card.answers, &chosen[index]) {
-            correct_cards += 1;
+const MONOSPACE: iced::Font = iced::Font::External {
+    name: "DejaVu Sans Mono",
+    bytes: include_bytes!("../fonts/DejaVuSansMono.ttf"),
+};
+
+const BOLD: iced::Font = iced::Font::External {
+    name: "DejaVu Sans Bold",
+    bytes: include_bytes!("../fonts/DejaVuSans-Bold.ttf"),
+};
+
+const ITALIC: iced::Font = iced::Font::External {
+    name: "DejaVu Sans Oblique",
+    bytes: include_bytes!("../fonts/DejaVuSans-Oblique.ttf"),
+};
+
+/// Card content written in Markdown, laid out as widgets.
+fn markdown<'a>(source: &str, size: u16) -> Element<'a, Message> {
+    markdown_blocks(markdown::parse(source), size).into()
+}
+
+fn markdown_blocks<'a>(blocks: Vec<Block>, size: u16) -> iced::widget::Column<'a, Message> {
+    blocks
+        .into_iter()
+        .fold(column![].spacing(8), |column, block| {
+            column.push(match block {
+                Block::Paragraph(spans) => markdown_spans(spans, size),
+                Block::Heading(level, mut spans) => {
+                    spans.iter_mut().for_each(|span| span.style.strong = true);
+                    markdown_spans(spans, size + 4 * 4u16.saturating_sub(level.into()))
+                }
+                Block::Code(code) => container(text(code).font(MONOSPACE).size(size - 2))
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(styling::code_style())
+                    .into(),
+                Block::List { start, items } => items
+                    .into_iter()
+                    .enumerate()
+                    .fold(column![].spacing(4), |list, (index, item)| {
+                        let marker = match start {
+                            Some(start) => format!("{}.", start + index as u64),
+                            None => "•".to_owned(),
+                        };
+                        list.push(
+                            row![text(marker).size(size), markdown_blocks(item, size)].spacing(6),
+                        )
+                    })
+                    .into(),
+            })
+        })
+}
+
+/// Lays styled text out word by word, so that it wraps like a paragraph.
+/// Inline formulas wrap along with the words, while display formulas break
+/// the paragraph and are centered on a line of their own.
+fn markdown_spans<'a>(spans: Vec<Span>, size: u16) -> Element<'a, Message> {
+    let mut lines = column![].spacing(8);
+    let mut words: Vec<Element<'a, Message>> = Vec::new();
+    for span in spans.iter() {
+        match span.style.math {
+            Some(MathMode::Inline) => {
+                // Lowered to share the baseline of the words around it.
+                let formula = Formula::layout(&span.text, size.into(), false)
+                    .with_min_ascent(0.8 * size as f32);
+                words.push(formula_canvas(formula));
+                continue;
+            }
+            Some(MathMode::Display) => {
+                if !words.is_empty() {
+                    lines =
+                        lines.push(Wrap::with_elements(std::mem::take(&mut words)).line_spacing(2));
+                }
+                let formula = Formula::layout(&span.text, size.into(), true);
+                lines = lines.push(
+                    container(formula_canvas(formula))
+                        .width(Length::Fill)
+                        .center_x(),
+                );
+                continue;
+            }
+            None => {}
         }
+        for word in span.text.split_inclusive(' ') {
+            let word_text = text(word).size(size);
+            words.push(match span.style {
+                style if style.code => container(word_text.font(MONOSPACE))
+                    .style(styling::code_style())
+                    .into(),
+                style if style.strong => word_text.font(BOLD).into(),
+                style if style.emphasis => word_text.font(ITALIC).into(),
+                _ => word_text.into(),
+            });
+        }
+    }
+    if !words.is_empty() {
+        lines = lines.push(Wrap::with_elements(words).line_spacing(2));
+    }
+    lines.into()
+}
+
+fn formula_canvas<'a>(formula: Formula) -> Element<'a, Message> {
+    let width = Length::Units(formula.width.ceil() as u16);
+    let height = Length::Units(formula.height().ceil() as u16);
+    Canvas::new(formula).width(width).height(height).into()
+}
+
+/// A choice between answers. Radio labels are plain text, so answers with
+/// math are laid out beside the answer's number instead.
+fn answer_radio<'a>(
+    index: usize,
+    answer: &str,
+    selected: Option<usize>,
+    on_select: impl FnOnce(usize) -> Message,
+) -> iced::widget::Row<'a, Message> {
+    if markdown::has_math(answer) {
+        row![
+            radio(format!("{})", index + 1), index, selected, on_select),
+            markdown(answer, 20)
+        ]
+        .spacing(10)
+    } else {
+        row![radio(
+            format!("{}) {}", index + 1, answer),
+            index,
+            selected,
+            on_select,
+        )]
+        .spacing(10)
     }
-    ((correct_cards) as f32 / (cards.len()) as f32) * (100) as f32
 }
 
 fn card_is_correct(answers: &Vec<Answer>, chosen: &Vec<bool>) -> bool {
@@ -1362,16 +4904,93 @@ fn card_is_correct(answers: &Vec<Answer>, chosen: &Vec<bool>) -> bool {
     true
 }
 
+/// Milliseconds as seconds, without trailing zeros.
+fn format_seconds(ms: u64) -> String {
+    let seconds = format!("{:.3}", ms as f32 / 1000.0);
+    seconds
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_owned()
+}
+
+/// Formats a duration as minutes and seconds, such as `4:05`.
+fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
 fn get_selected_deck_info(deck: &Deck) -> (String, String) {
     (deck.title.clone(), deck.description.clone())
 }
 
+/// Deck settings as typed, kept apart so that half-typed values can show.
+#[derive(Clone, Debug, Default)]
+struct DeckSettingsForm {
+    new_cards_per_day: String,
+    reviews_per_day: String,
+    learning_steps: String,
+    leech_threshold: String,
+}
+
+impl From<&DeckSettings> for DeckSettingsForm {
+    fn from(settings: &DeckSettings) -> Self {
+        DeckSettingsForm {
+            new_cards_per_day: settings.new_cards_per_day.to_string(),
+            reviews_per_day: settings.reviews_per_day.to_string(),
+            learning_steps: preferences::format_learning_steps(&settings.learning_steps),
+            leech_threshold: settings.leech_threshold.to_string(),
+        }
+    }
+}
+
+/// A round in progress, saved so that it can be left and resumed later,
+/// even after a restart. Only the latest round is kept.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct SavedRound {
+    title: String,
+    cards: Vec<Card>,
+    deck_ids: Vec<String>,
+    selected_card: usize,
+    selected_answers: Vec<Vec<bool>>,
+    response_times: Vec<Option<u64>>,
+    ratings: Vec<Option<Rating>>,
+    flashcards: bool,
+    /// How long the current card had been shown, in milliseconds.
+    card_elapsed_ms: u64,
+    saved_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl SavedRound {
+    /// Whether every card has its answers, timing and rating, and the
+    /// current card is among them, as a file edited or cut short may not.
+    fn is_consistent(&self) -> bool {
+        let cards = self.cards.len();
+        self.selected_card < cards
+            && self.deck_ids.len() == cards
+            && self.response_times.len() == cards
+            && self.ratings.len() == cards
+            && self.selected_answers.len() == cards
+            && self
+                .selected_answers
+                .iter()
+                .zip(self.cards.iter())
+                .all(|(selected, card)| selected.len() == card.answers.len())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct EditDeck {
     #[serde(default = "empty_id")]
     id: String,
     title: String,
     description: String,
+    /// Folder path written out with `::` between levels.
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    is_public: bool,
     cards: Vec<EditCard>,
 }
 
@@ -1385,6 +5004,9 @@ impl EditDeck {
             id: "".to_owned(),
             title: "".to_owned(),
             description: "".to_owned(),
+            folder: "".to_owned(),
+            tags: Vec::new(),
+            is_public: false,
             cards: Vec::new(),
         }
     }
@@ -1396,6 +5018,9 @@ impl From<&client::Deck> for EditDeck {
             id: deck.id.clone(),
             title: deck.title.clone(),
             description: deck.description.clone(),
+            folder: folders::format_path(&deck.folder),
+            tags: deck.tags.clone(),
+            is_public: deck.is_public,
             cards: deck.cards.iter().map(|card| card.into()).collect(),
         }
     }
@@ -1410,6 +5035,18 @@ fn deck_from_edit_deck(edit_deck: &EditDeck) -> client::Deck {
         },
         title: edit_deck.title.clone(),
         description: edit_deck.description.clone(),
+        owner_id: String::new(),
+        folder: folders::parse_path(&edit_deck.folder),
+        tags: edit_deck
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_owned())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        card_count: edit_deck.cards.len(),
+        created_at: None,
+        is_public: edit_deck.is_public,
+        author: String::new(),
         cards: edit_deck
             .cards
             .iter()
@@ -1420,17 +5057,23 @@ fn deck_from_edit_deck(edit_deck: &EditDeck) -> client::Deck {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct EditCard {
+    #[serde(default = "empty_id")]
+    id: String,
     title: String,
     answers: Vec<EditAnswer>,
     explanation: String,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 impl EditCard {
     fn new() -> Self {
         EditCard {
+            id: "".to_owned(),
             title: "".to_owned(),
             answers: Vec::new(),
             explanation: "".to_owned(),
+            tags: Vec::new(),
         }
     }
 }
@@ -1438,15 +5081,18 @@ impl EditCard {
 impl From<&client::Card> for EditCard {
     fn from(card: &client::Card) -> Self {
         EditCard {
+            id: card.id.clone(),
             title: card.title.clone(),
             answers: card.answers.iter().map(|answer| answer.into()).collect(),
             explanation: card.explanation.clone(),
+            tags: card.tags.clone(),
         }
     }
 }
 
 fn card_from_edit_card(edit_card: &EditCard) -> client::Card {
     client::Card {
+        id: edit_card.id.clone(),
         title: edit_card.title.clone(),
         answers: edit_card
             .answers
@@ -1454,6 +5100,12 @@ fn card_from_edit_card(edit_card: &EditCard) -> client::Card {
             .map(|edit_answer| answer_from_edit_answer(edit_answer))
             .collect(),
         explanation: edit_card.explanation.clone(),
+        tags: edit_card
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_owned())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
     }
 }
 
@@ -1489,18 +5141,30 @@ fn answer_from_edit_answer(edit_answer: &EditAnswer) -> client::Answer {
     }
 }
 
+fn export_deck_to_file(path: &str, deck: &EditDeck) -> Result<(), Error> {
+    let json_str = serde_json::to_string_pretty(deck)
+        .map_err(|err| client::Error::PayloadError(format!("could not serialize deck: {}", err)))?;
+
+    std::fs::write(path, json_str)
+        .map_err(|err| client::Error::PayloadError(format!("could not write {}: {}", path, err)))
+}
+
 fn import_deck_from_file(path: &str) -> Result<EditDeck, Error> {
     let deck_file = std::fs::read_to_string(path);
-    if deck_file.is_err() {
-        dbg!(deck_file.err());
-        return Err(client::Error::PayloadError);
+    if let Err(err) = deck_file {
+        return Err(client::Error::PayloadError(format!(
+            "could not read {}: {}",
+            path, err
+        )));
     }
 
     let json_str = deck_file.unwrap();
     let deck = serde_json::from_str::<EditDeck>(&json_str);
-    if deck.is_err() {
-        dbg!(deck.err());
-        return Err(client::Error::PayloadError);
+    if let Err(err) = deck {
+        return Err(client::Error::PayloadError(format!(
+            "could not parse {}: {}",
+            path, err
+        )));
     }
 
     return Ok(deck.unwrap());
@@ -0,0 +1,328 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Parsed sources kept for redrawing, before the cache starts over.
+const CACHE_SIZE: usize = 256;
+
+thread_local! {
+    static PARSED: RefCell<HashMap<String, Vec<Block>>> = RefCell::new(HashMap::new());
+}
+
+/// A block of Markdown card content, ready to be laid out as widgets.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Paragraph(Vec<Span>),
+    /// A heading with its level, 1 being the largest.
+    Heading(u8, Vec<Span>),
+    /// A fenced or indented code block, shown in a monospace font.
+    Code(String),
+    /// A list numbered from `start`, or a bullet list without one.
+    List {
+        start: Option<u64>,
+        items: Vec<Vec<Block>>,
+    },
+}
+
+/// How TeX math between dollar signs is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathMode {
+    /// `$...$`, set within the line.
+    Inline,
+    /// `$$...$$`, set larger on a line of its own.
+    Display,
+}
+
+/// A run of text sharing one style, or the TeX source of a formula.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpanStyle {
+    pub strong: bool,
+    pub emphasis: bool,
+    pub code: bool,
+    pub math: Option<MathMode>,
+}
+
+/// Collects the inline events of a paragraph, heading or tight list item.
+#[derive(Default)]
+struct Inline {
+    spans: Vec<Span>,
+    style: SpanStyle,
+}
+
+impl Inline {
+    /// Takes in `event`, returning false when it is not an inline event.
+    fn push(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Text(text) | Event::InlineHtml(text) => self.push_text(text, self.style),
+            Event::Code(code) => self.push_text(
+                code,
+                SpanStyle {
+                    code: true,
+                    ..self.style
+                },
+            ),
+            Event::InlineMath(source) => self.push_math(source, MathMode::Inline),
+            Event::DisplayMath(source) => self.push_math(source, MathMode::Display),
+            Event::SoftBreak | Event::HardBreak => self.push_text(" ", self.style),
+            Event::Start(Tag::Emphasis) => self.style.emphasis = true,
+            Event::End(TagEnd::Emphasis) => self.style.emphasis = false,
+            Event::Start(Tag::Strong) => self.style.strong = true,
+            Event::End(TagEnd::Strong) => self.style.strong = false,
+            // Links and struck text keep their words, unstyled, and images
+            // their alt text.
+            Event::Start(Tag::Link { .. } | Tag::Image { .. } | Tag::Strikethrough)
+            | Event::End(TagEnd::Link | TagEnd::Image | TagEnd::Strikethrough) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn push_text(&mut self, text: &str, style: SpanStyle) {
+        match self.spans.last_mut() {
+            Some(last) if last.style == style && style.math.is_none() => last.text.push_str(text),
+            _ => self.spans.push(Span {
+                text: text.to_owned(),
+                style,
+            }),
+        }
+    }
+
+    /// Adds a formula, which never merges with the spans around it.
+    fn push_math(&mut self, source: &str, mode: MathMode) {
+        self.spans.push(Span {
+            text: source.to_owned(),
+            style: SpanStyle {
+                math: Some(mode),
+                ..SpanStyle::default()
+            },
+        });
+    }
+
+    fn take(&mut self) -> Vec<Span> {
+        std::mem::take(&mut self.spans)
+    }
+}
+
+/// Parses card content written in Markdown, with TeX math between dollar
+/// signs. Text without any Markdown comes back as a single plain paragraph.
+/// Each source is parsed once, as views are rebuilt many times a second.
+pub fn parse(source: &str) -> Vec<Block> {
+    if let Some(blocks) = PARSED.with(|parsed| parsed.borrow().get(source).cloned()) {
+        return blocks;
+    }
+
+    let blocks = blocks(&mut parser(source));
+    PARSED.with(|parsed| {
+        let mut parsed = parsed.borrow_mut();
+        if parsed.len() >= CACHE_SIZE {
+            parsed.clear();
+        }
+        parsed.insert(source.to_owned(), blocks.clone());
+    });
+    blocks
+}
+
+/// Whether `source` holds a formula, rather than only stray dollar signs
+/// such as in prices.
+pub fn has_math(source: &str) -> bool {
+    blocks_have_math(&parse(source))
+}
+
+fn blocks_have_math(blocks: &[Block]) -> bool {
+    blocks.iter().any(|block| match block {
+        Block::Paragraph(spans) | Block::Heading(_, spans) => {
+            spans.iter().any(|span| span.style.math.is_some())
+        }
+        Block::Code(_) => false,
+        Block::List { items, .. } => items.iter().any(|item| blocks_have_math(item)),
+    })
+}
+
+fn parser(source: &str) -> Parser<'_> {
+    Parser::new_ext(source, Options::ENABLE_STRIKETHROUGH | Options::ENABLE_MATH)
+}
+
+/// Reads blocks up to the end of the enclosing list item, or of the source.
+fn blocks(events: &mut Parser) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    // Tight list items hold their text without a paragraph around it.
+    let mut loose = Inline::default();
+
+    while let Some(event) = events.next() {
+        if loose.push(&event) {
+            continue;
+        }
+        if !loose.spans.is_empty() {
+            blocks.push(Block::Paragraph(loose.take()));
+        }
+
+        match event {
+            Event::Start(Tag::Paragraph) => blocks.push(Block::Paragraph(spans(events))),
+            Event::Start(Tag::Heading { level, .. }) => {
+                blocks.push(Block::Heading(level as u8, spans(events)))
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                let mut code = String::new();
+                for event in events.by_ref() {
+                    match event {
+                        Event::Text(text) => code.push_str(&text),
+                        _ => break,
+                    }
+                }
+                blocks.push(Block::Code(code.trim_end_matches('\n').to_owned()));
+            }
+            Event::Start(Tag::List(start)) => {
+                let mut items = Vec::new();
+                while let Some(event) = events.next() {
+                    match event {
+                        Event::Start(Tag::Item) => items.push(self::blocks(events)),
+                        _ => break,
+                    }
+                }
+                blocks.push(Block::List { start, items });
+            }
+            Event::End(TagEnd::Item) => break,
+            _ => {}
+        }
+    }
+
+    if !loose.spans.is_empty() {
+        blocks.push(Block::Paragraph(loose.take()));
+    }
+    blocks
+}
+
+/// Reads the inline content of a paragraph or heading up to its end.
+fn spans(events: &mut Parser) -> Vec<Span> {
+    let mut inline = Inline::default();
+    for event in events.by_ref() {
+        if !inline.push(&event) {
+            break;
+        }
+    }
+    inline.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: SpanStyle) -> Span {
+        Span {
+            text: text.to_owned(),
+            style,
+        }
+    }
+
+    fn plain(text: &str) -> Span {
+        span(text, SpanStyle::default())
+    }
+
+    fn paragraph(text: &str) -> Vec<Block> {
+        vec![Block::Paragraph(vec![plain(text)])]
+    }
+
+    #[test]
+    fn plain_text_is_one_paragraph() {
+        assert_eq!(parse("What is 2 + 2?"), paragraph("What is 2 + 2?"));
+    }
+
+    #[test]
+    fn inline_styles_become_spans() {
+        assert_eq!(
+            parse("*some* **bold** `code`"),
+            vec![Block::Paragraph(vec![
+                span(
+                    "some",
+                    SpanStyle {
+                        emphasis: true,
+                        ..SpanStyle::default()
+                    }
+                ),
+                plain(" "),
+                span(
+                    "bold",
+                    SpanStyle {
+                        strong: true,
+                        ..SpanStyle::default()
+                    }
+                ),
+                plain(" "),
+                span(
+                    "code",
+                    SpanStyle {
+                        code: true,
+                        ..SpanStyle::default()
+                    }
+                ),
+            ])]
+        );
+    }
+
+    #[test]
+    fn fenced_code_keeps_its_lines() {
+        assert_eq!(
+            parse("Run:\n\n```rust\nfn main() {}\nmain();\n```"),
+            vec![
+                Block::Paragraph(vec![plain("Run:")]),
+                Block::Code("fn main() {}\nmain();".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_nest_and_keep_their_start() {
+        assert_eq!(
+            parse("3. one\n   - inner\n4. two"),
+            vec![Block::List {
+                start: Some(3),
+                items: vec![
+                    vec![
+                        Block::Paragraph(vec![plain("one")]),
+                        Block::List {
+                            start: None,
+                            items: vec![paragraph("inner")],
+                        },
+                    ],
+                    paragraph("two"),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn tight_and_loose_items_read_alike() {
+        let expected = vec![Block::List {
+            start: None,
+            items: vec![paragraph("a"), paragraph("b")],
+        }];
+        assert_eq!(parse("- a\n- b"), expected);
+        assert_eq!(parse("- a\n\n- b"), expected);
+    }
+
+    #[test]
+    fn images_keep_their_alt_text() {
+        assert_eq!(
+            parse("See ![a cat](cat.png) here"),
+            paragraph("See a cat here")
+        );
+    }
+
+    #[test]
+    fn block_html_is_dropped() {
+        assert_eq!(parse("<div>\nhidden\n</div>\n\nshown"), paragraph("shown"));
+    }
+
+    #[test]
+    fn math_is_told_apart_from_prices() {
+        assert!(!has_math("$5 and $6"));
+        assert!(has_math("Solve $x^2 = 4$"));
+        assert!(has_math("- $$\\frac{1}{2}$$"));
+    }
+}
@@ -0,0 +1,1253 @@
+use iced::widget::canvas::{self, Cursor, Frame, Geometry, Path, Stroke, Text};
+use iced::{alignment, Point, Rectangle, Theme};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use ttf_parser::Face;
+
+/// DejaVu Math TeX Gyre, which holds the math alphabets and symbols. It is
+/// under the GUST Font License, see `fonts/GUST-FONT-LICENSE`.
+const FONT_BYTES: &[u8] = include_bytes!("../fonts/DejaVuMathTeXGyre.ttf");
+
+pub const FONT: iced::Font = iced::Font::External {
+    name: "DejaVu Math TeX Gyre",
+    bytes: FONT_BYTES,
+};
+
+/// Height of fraction bars and of the middle of operators, in em.
+const AXIS: f32 = 0.25;
+const RULE: f32 = 0.05;
+/// Sizes of scripts, and of scripts within scripts.
+const SCRIPT_SCALES: [f32; 3] = [1.0, 0.7, 0.5];
+const THIN: f32 = 3.0 / 18.0;
+const MEDIUM: f32 = 4.0 / 18.0;
+const THICK: f32 = 5.0 / 18.0;
+/// How deep groups and arguments may nest, past which the rest of the
+/// source is shown as written.
+const MAX_DEPTH: usize = 32;
+/// Laid out formulas kept for redrawing, before the cache starts over.
+const CACHE_SIZE: usize = 256;
+
+thread_local! {
+    static LAYOUTS: RefCell<HashMap<(String, u32, bool), Formula>> = RefCell::new(HashMap::new());
+}
+
+fn face() -> Option<&'static Face<'static>> {
+    static FACE: OnceLock<Option<Face<'static>>> = OnceLock::new();
+    FACE.get_or_init(|| Face::parse(FONT_BYTES, 0).ok())
+        .as_ref()
+}
+
+/// Something drawn in a laid out formula, placed from its top left corner.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    /// A character, with `top` where the line box of `size` text starts.
+    Glyph {
+        character: char,
+        x: f32,
+        top: f32,
+        size: f32,
+    },
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+    },
+}
+
+impl Item {
+    fn shift(&mut self, dx: f32, dy: f32) {
+        match self {
+            Item::Glyph { x, top, .. } => {
+                *x += dx;
+                *top += dy;
+            }
+            Item::Line { from, to, .. } => {
+                *from = (from.0 + dx, from.1 + dy);
+                *to = (to.0 + dx, to.1 + dy);
+            }
+        }
+    }
+}
+
+/// A formula laid out for drawing, `ascent` above and `descent` below its
+/// baseline.
+#[derive(Clone, Debug, Default)]
+pub struct Formula {
+    pub items: Vec<Item>,
+    pub width: f32,
+    pub ascent: f32,
+    pub descent: f32,
+}
+
+impl Formula {
+    /// Lays out the TeX math in `source` with an em of `size` pixels.
+    /// Display formulas are set larger, with limits above and below big
+    /// operators. Formulas are cached, as views are rebuilt many times a
+    /// second.
+    pub fn layout(source: &str, size: f32, display: bool) -> Formula {
+        let key = (source.to_owned(), size.to_bits(), display);
+        if let Some(formula) = LAYOUTS.with(|layouts| layouts.borrow().get(&key).cloned()) {
+            return formula;
+        }
+
+        let formula = Formula::lay_out(source, size, display);
+        LAYOUTS.with(|layouts| {
+            let mut layouts = layouts.borrow_mut();
+            if layouts.len() >= CACHE_SIZE {
+                layouts.clear();
+            }
+            layouts.insert(key, formula.clone());
+        });
+        formula
+    }
+
+    fn lay_out(source: &str, size: f32, display: bool) -> Formula {
+        let nodes = Parser::new(source).list(End::Source);
+        let layouter = Layouter { face: face() };
+        let style = Style {
+            base: size,
+            level: 0,
+            display,
+        };
+        let mut formula = layouter.list(&nodes, style).0;
+
+        let padding = size * 0.1;
+        formula.ascent += padding;
+        formula.descent += padding;
+        for item in formula.items.iter_mut() {
+            item.shift(0.0, formula.ascent);
+        }
+        formula
+    }
+
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+
+    /// Lowers the formula to at least `ascent` below its top, so that its
+    /// baseline lines up with the text around it.
+    pub fn with_min_ascent(mut self, ascent: f32) -> Self {
+        if self.ascent < ascent {
+            for item in self.items.iter_mut() {
+                item.shift(0.0, ascent - self.ascent);
+            }
+            self.ascent = ascent;
+        }
+        self
+    }
+
+    /// Places `other` with its baseline `shift` below this one's, `x` from
+    /// the left.
+    fn append(&mut self, mut other: Formula, x: f32, shift: f32) {
+        for item in other.items.iter_mut() {
+            item.shift(x, shift);
+        }
+        self.items.append(&mut other.items);
+        self.width = self.width.max(x + other.width);
+        self.ascent = self.ascent.max(other.ascent - shift);
+        self.descent = self.descent.max(other.descent + shift);
+    }
+
+    /// Places `other` centered across `width`, as for limits and fractions.
+    fn append_centered(&mut self, other: Formula, width: f32, shift: f32) {
+        let x = (width - other.width) / 2.0;
+        self.append(other, x, shift);
+    }
+
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32) {
+        self.items.push(Item::Line { from, to, width });
+    }
+}
+
+/// How atoms are spaced from their neighbours, as in TeX.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Class {
+    Ord,
+    Op,
+    Bin,
+    Rel,
+    Open,
+    Close,
+    Punct,
+}
+
+fn spacing(left: Class, right: Class) -> f32 {
+    use Class::*;
+    match (left, right) {
+        (Open, _) | (_, Close) | (_, Punct) | (Rel, Rel) => 0.0,
+        (Rel, _) | (_, Rel) => THICK,
+        (Bin, _) | (_, Bin) => MEDIUM,
+        (Punct, _) | (Op, Ord) | (Ord, Op) | (Op, Op) | (Close, Op) => THIN,
+        _ => 0.0,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Accent {
+    Bar,
+    Hat,
+    Vector,
+    Dot,
+    Tilde,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Char(char, Class),
+    /// Upright text, such as `\text` contents.
+    Text(String),
+    /// Space in em, which may be negative.
+    Space(f32),
+    Group(Vec<Node>),
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Box<Node>>,
+        sup: Option<Box<Node>>,
+    },
+    Fraction(Box<Node>, Box<Node>),
+    Root {
+        body: Box<Node>,
+        index: Option<Box<Node>>,
+    },
+    /// A big symbol such as a sum, or a function name such as `lim`.
+    Operator {
+        text: String,
+        big: bool,
+        limits: bool,
+    },
+    Accent(Accent, Box<Node>),
+    /// Contents between `\left` and `\right`, whose delimiters grow with them.
+    Delimited(Option<char>, Vec<Node>, Option<char>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum End {
+    Source,
+    Brace,
+    Bracket,
+    Right,
+}
+
+const GREEK: [(&str, char); 40] = [
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ϵ'),
+    ("varepsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("vartheta", 'ϑ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("pi", 'π'),
+    ("varpi", 'ϖ'),
+    ("rho", 'ρ'),
+    ("varrho", 'ϱ'),
+    ("sigma", 'σ'),
+    ("varsigma", 'ς'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'ϕ'),
+    ("varphi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Theta", 'Θ'),
+    ("Lambda", 'Λ'),
+    ("Xi", 'Ξ'),
+    ("Pi", 'Π'),
+    ("Sigma", 'Σ'),
+    ("Upsilon", 'Υ'),
+    ("Phi", 'Φ'),
+    ("Psi", 'Ψ'),
+    ("Omega", 'Ω'),
+];
+
+const SYMBOLS: [(&str, char, Class); 77] = [
+    ("pm", '±', Class::Bin),
+    ("mp", '∓', Class::Bin),
+    ("times", '×', Class::Bin),
+    ("div", '÷', Class::Bin),
+    ("cdot", '⋅', Class::Bin),
+    ("ast", '∗', Class::Bin),
+    ("star", '⋆', Class::Bin),
+    ("circ", '∘', Class::Bin),
+    ("bullet", '∙', Class::Bin),
+    ("cup", '∪', Class::Bin),
+    ("cap", '∩', Class::Bin),
+    ("setminus", '∖', Class::Bin),
+    ("oplus", '⊕', Class::Bin),
+    ("otimes", '⊗', Class::Bin),
+    ("wedge", '∧', Class::Bin),
+    ("land", '∧', Class::Bin),
+    ("vee", '∨', Class::Bin),
+    ("lor", '∨', Class::Bin),
+    ("leq", '≤', Class::Rel),
+    ("le", '≤', Class::Rel),
+    ("geq", '≥', Class::Rel),
+    ("ge", '≥', Class::Rel),
+    ("neq", '≠', Class::Rel),
+    ("ne", '≠', Class::Rel),
+    ("approx", '≈', Class::Rel),
+    ("equiv", '≡', Class::Rel),
+    ("sim", '∼', Class::Rel),
+    ("simeq", '≃', Class::Rel),
+    ("cong", '≅', Class::Rel),
+    ("propto", '∝', Class::Rel),
+    ("ll", '≪', Class::Rel),
+    ("gg", '≫', Class::Rel),
+    ("in", '∈', Class::Rel),
+    ("notin", '∉', Class::Rel),
+    ("ni", '∋', Class::Rel),
+    ("subset", '⊂', Class::Rel),
+    ("supset", '⊃', Class::Rel),
+    ("subseteq", '⊆', Class::Rel),
+    ("supseteq", '⊇', Class::Rel),
+    ("to", '→', Class::Rel),
+    ("rightarrow", '→', Class::Rel),
+    ("leftarrow", '←', Class::Rel),
+    ("gets", '←', Class::Rel),
+    ("leftrightarrow", '↔', Class::Rel),
+    ("Rightarrow", '⇒', Class::Rel),
+    ("Leftarrow", '⇐', Class::Rel),
+    ("Leftrightarrow", '⇔', Class::Rel),
+    ("implies", '⟹', Class::Rel),
+    ("iff", '⟺', Class::Rel),
+    ("mapsto", '↦', Class::Rel),
+    ("perp", '⊥', Class::Rel),
+    ("parallel", '∥', Class::Rel),
+    ("mid", '∣', Class::Rel),
+    ("infty", '∞', Class::Ord),
+    ("partial", '∂', Class::Ord),
+    ("nabla", '∇', Class::Ord),
+    ("forall", '∀', Class::Ord),
+    ("exists", '∃', Class::Ord),
+    ("emptyset", '∅', Class::Ord),
+    ("varnothing", '∅', Class::Ord),
+    ("neg", '¬', Class::Ord),
+    ("angle", '∠', Class::Ord),
+    ("hbar", 'ℏ', Class::Ord),
+    ("ell", 'ℓ', Class::Ord),
+    ("prime", '′', Class::Ord),
+    ("degree", '°', Class::Ord),
+    ("ldots", '…', Class::Ord),
+    ("dots", '…', Class::Ord),
+    ("cdots", '⋯', Class::Ord),
+    ("vdots", '⋮', Class::Ord),
+    ("langle", '⟨', Class::Open),
+    ("lfloor", '⌊', Class::Open),
+    ("lceil", '⌈', Class::Open),
+    ("rangle", '⟩', Class::Close),
+    ("rfloor", '⌋', Class::Close),
+    ("rceil", '⌉', Class::Close),
+    ("colon", ':', Class::Punct),
+];
+
+/// Big operators, and whether they take limits above and below.
+const BIG_OPERATORS: [(&str, char, bool); 10] = [
+    ("sum", '∑', true),
+    ("prod", '∏', true),
+    ("coprod", '∐', true),
+    ("bigcup", '⋃', true),
+    ("bigcap", '⋂', true),
+    ("int", '∫', false),
+    ("iint", '∬', false),
+    ("iiint", '∭', false),
+    ("oint", '∮', false),
+    ("bigoplus", '⨁', true),
+];
+
+const LIMIT_FUNCTIONS: [&str; 7] = ["lim", "max", "min", "sup", "inf", "det", "gcd"];
+
+const FUNCTIONS: [&str; 22] = [
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "deg", "dim", "ker", "arg", "hom", "Pr",
+];
+
+/// Letters as set in math, in the italic math alphabet where the font has it.
+fn math_italic(c: char) -> char {
+    let code = match c {
+        'h' => 0x210E,
+        'a'..='z' => 0x1D44E + (c as u32 - 'a' as u32),
+        'A'..='Z' => 0x1D434 + (c as u32 - 'A' as u32),
+        'α'..='ω' => 0x1D6FC + (c as u32 - 'α' as u32),
+        _ => return c,
+    };
+    char::from_u32(code).unwrap_or(c)
+}
+
+fn double_struck(c: char) -> char {
+    match c {
+        'C' => 'ℂ',
+        'H' => 'ℍ',
+        'N' => 'ℕ',
+        'P' => 'ℙ',
+        'Q' => 'ℚ',
+        'R' => 'ℝ',
+        'Z' => 'ℤ',
+        _ => c,
+    }
+}
+
+/// Reads TeX math into nodes. Anything it does not understand is shown as
+/// written rather than rejected.
+struct Parser<'a> {
+    rest: &'a str,
+    /// Groups and arguments being read.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Parser {
+            rest: source,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest.chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.rest = &self.rest[c.len_utf8()..];
+        Some(c)
+    }
+
+    fn skip_spaces(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// Reads the name of a command after its backslash.
+    fn command_name(&mut self) -> &'a str {
+        let length = self
+            .rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(self.rest.len());
+        let length = match length {
+            0 => self.peek().map_or(0, char::len_utf8),
+            length => length,
+        };
+        let (name, rest) = self.rest.split_at(length);
+        self.rest = rest;
+        name
+    }
+
+    fn at_command(&self, name: &str) -> bool {
+        let rest = self
+            .rest
+            .strip_prefix('\\')
+            .and_then(|rest| rest.strip_prefix(name));
+        matches!(rest, Some(rest) if !rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+    }
+
+    /// Takes the rest of the source as written, once it nests too deep to
+    /// be read without overflowing the stack.
+    fn too_deep(&mut self) -> Option<Node> {
+        if self.depth < MAX_DEPTH {
+            return None;
+        }
+        let rest = std::mem::take(&mut self.rest);
+        Some(Node::Text(rest.to_owned()))
+    }
+
+    fn list(&mut self, end: End) -> Vec<Node> {
+        if let Some(node) = self.too_deep() {
+            return vec![node];
+        }
+        self.depth += 1;
+        let nodes = self.list_items(end);
+        self.depth -= 1;
+        nodes
+    }
+
+    fn list_items(&mut self, end: End) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '}' if end == End::Brace => {
+                    self.next();
+                    break;
+                }
+                ']' if end == End::Bracket => {
+                    self.next();
+                    break;
+                }
+                '\\' if end == End::Right && self.at_command("right") => {
+                    self.rest = &self.rest["\\right".len()..];
+                    break;
+                }
+                '^' | '_' => {
+                    self.next();
+                    let script = self.argument();
+                    attach(&mut nodes, script, c == '^');
+                }
+                '\'' => {
+                    self.next();
+                    attach(&mut nodes, Node::Char('′', Class::Ord), true);
+                }
+                _ => {
+                    if let Some(node) = self.atom() {
+                        nodes.push(node);
+                    }
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Reads the argument of a command or script: a group or a single atom.
+    /// A missing one is empty, leaving any closing brace to its group.
+    fn argument(&mut self) -> Node {
+        if let Some(node) = self.too_deep() {
+            return node;
+        }
+        self.skip_spaces();
+        match self.peek() {
+            Some('{') => {
+                self.next();
+                Node::Group(self.list(End::Brace))
+            }
+            Some('}') | None => Node::Group(Vec::new()),
+            Some(_) => {
+                self.depth += 1;
+                let node = self.atom();
+                self.depth -= 1;
+                node.unwrap_or(Node::Group(Vec::new()))
+            }
+        }
+    }
+
+    /// Reads the raw text of a group, as for `\text{...}`.
+    fn raw_argument(&mut self) -> String {
+        self.skip_spaces();
+        if self.peek() != Some('{') {
+            return self.next().map(String::from).unwrap_or_default();
+        }
+        self.next();
+        let mut depth = 0;
+        let mut text = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        let c = self.next()?;
+        let node = match c {
+            c if c.is_whitespace() => return None,
+            '{' => Node::Group(self.list(End::Brace)),
+            '}' => return None,
+            '\\' => return self.command(),
+            '~' => Node::Space(THICK),
+            '&' => Node::Space(1.0),
+            'a'..='z' | 'A'..='Z' => Node::Char(math_italic(c), Class::Ord),
+            '+' => Node::Char('+', Class::Bin),
+            '-' => Node::Char('−', Class::Bin),
+            '*' => Node::Char('∗', Class::Bin),
+            '=' | '<' | '>' | ':' => Node::Char(c, Class::Rel),
+            ',' | ';' => Node::Char(c, Class::Punct),
+            '(' | '[' => Node::Char(c, Class::Open),
+            ')' | ']' | '!' | '?' => Node::Char(c, Class::Close),
+            _ => Node::Char(c, Class::Ord),
+        };
+        Some(node)
+    }
+
+    fn command(&mut self) -> Option<Node> {
+        let name = self.command_name();
+        let node = match name {
+            "" => return None,
+            "," => Node::Space(THIN),
+            ":" | ">" => Node::Space(MEDIUM),
+            ";" => Node::Space(THICK),
+            " " => Node::Space(THICK),
+            "!" => Node::Space(-THIN),
+            "quad" => Node::Space(1.0),
+            "qquad" => Node::Space(2.0),
+            "\\" => Node::Space(1.0),
+            "{" => Node::Char('{', Class::Open),
+            "}" => Node::Char('}', Class::Close),
+            "|" => Node::Char('‖', Class::Ord),
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                Node::Fraction(Box::new(numerator), Box::new(denominator))
+            }
+            "sqrt" => {
+                self.skip_spaces();
+                let index = match self.peek() {
+                    Some('[') => {
+                        self.next();
+                        Some(Box::new(Node::Group(self.list(End::Bracket))))
+                    }
+                    _ => None,
+                };
+                Node::Root {
+                    body: Box::new(self.argument()),
+                    index,
+                }
+            }
+            "text" | "textrm" | "textit" | "mathrm" | "mbox" => Node::Text(self.raw_argument()),
+            "operatorname" => Node::Operator {
+                text: self.raw_argument(),
+                big: false,
+                limits: false,
+            },
+            "mathbb" => Node::Text(self.raw_argument().chars().map(double_struck).collect()),
+            "mathbf" | "mathit" | "mathcal" | "boldsymbol" | "displaystyle" => {
+                return Some(self.argument())
+            }
+            "left" => {
+                let left = self.delimiter();
+                let body = self.list(End::Right);
+                let right = self.delimiter();
+                Node::Delimited(left, body, right)
+            }
+            "right" => {
+                self.delimiter();
+                return None;
+            }
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" => return None,
+            "bar" | "overline" => Node::Accent(Accent::Bar, Box::new(self.argument())),
+            "hat" | "widehat" => Node::Accent(Accent::Hat, Box::new(self.argument())),
+            "vec" | "overrightarrow" => Node::Accent(Accent::Vector, Box::new(self.argument())),
+            "dot" => Node::Accent(Accent::Dot, Box::new(self.argument())),
+            "tilde" | "widetilde" => Node::Accent(Accent::Tilde, Box::new(self.argument())),
+            name if LIMIT_FUNCTIONS.contains(&name) || FUNCTIONS.contains(&name) => {
+                Node::Operator {
+                    text: name.to_owned(),
+                    big: false,
+                    limits: LIMIT_FUNCTIONS.contains(&name),
+                }
+            }
+            name => {
+                if let Some((_, symbol, limits)) = BIG_OPERATORS.iter().find(|op| op.0 == name) {
+                    Node::Operator {
+                        text: symbol.to_string(),
+                        big: true,
+                        limits: *limits,
+                    }
+                } else if let Some((_, letter)) = GREEK.iter().find(|greek| greek.0 == name) {
+                    Node::Char(math_italic(*letter), Class::Ord)
+                } else if let Some((_, symbol, class)) = SYMBOLS.iter().find(|s| s.0 == name) {
+                    Node::Char(*symbol, *class)
+                } else if name.len() == 1 {
+                    // Escaped characters such as `\%` and `\$`.
+                    Node::Char(name.chars().next().unwrap_or(' '), Class::Ord)
+                } else {
+                    Node::Text(format!("\\{}", name))
+                }
+            }
+        };
+        Some(node)
+    }
+
+    /// Reads the delimiter after `\left` or `\right`, `.` standing for none.
+    fn delimiter(&mut self) -> Option<char> {
+        self.skip_spaces();
+        match self.next()? {
+            '.' => None,
+            '\\' => match self.command_name() {
+                "{" | "lbrace" => Some('{'),
+                "}" | "rbrace" => Some('}'),
+                "|" => Some('‖'),
+                name => SYMBOLS.iter().find(|s| s.0 == name).map(|s| s.1),
+            },
+            c => Some(c),
+        }
+    }
+}
+
+/// Attaches a script to the last node, grouping it with any script already
+/// there, as for a prime followed by a power. Repeated scripts share one
+/// group rather than nesting.
+fn attach(nodes: &mut Vec<Node>, script: Node, superscript: bool) {
+    let (base, mut sub, mut sup) = match nodes.pop() {
+        Some(Node::Scripts { base, sub, sup }) => (base, sub, sup),
+        Some(node) => (Box::new(node), None, None),
+        None => (Box::new(Node::Group(Vec::new())), None, None),
+    };
+    let slot = if superscript { &mut sup } else { &mut sub };
+    *slot = Some(Box::new(match slot.take().map(|existing| *existing) {
+        Some(Node::Group(mut scripts)) => {
+            scripts.push(script);
+            Node::Group(scripts)
+        }
+        Some(existing) => Node::Group(vec![existing, script]),
+        None => script,
+    }));
+    nodes.push(Node::Scripts { base, sub, sup });
+}
+
+#[derive(Clone, Copy)]
+struct Style {
+    base: f32,
+    /// 0 for the main line, 1 for scripts and 2 for scripts of scripts.
+    level: usize,
+    display: bool,
+}
+
+impl Style {
+    fn size(&self) -> f32 {
+        self.base * SCRIPT_SCALES[self.level.min(2)]
+    }
+
+    fn script(&self) -> Style {
+        Style {
+            level: self.level + 1,
+            display: false,
+            ..*self
+        }
+    }
+
+    /// The style of fraction parts: the main size in display formulas,
+    /// script size within the line.
+    fn fraction(&self) -> Style {
+        if self.display {
+            Style {
+                display: false,
+                ..*self
+            }
+        } else {
+            self.script()
+        }
+    }
+}
+
+struct Layouter<'a> {
+    face: Option<&'a Face<'a>>,
+}
+
+impl<'a> Layouter<'a> {
+    /// Advance, ascent and descent of `c` in em.
+    fn metrics(&self, c: char) -> (f32, f32, f32) {
+        let face = match &self.face {
+            Some(face) => face,
+            None => return (0.5, 0.7, 0.0),
+        };
+        let em = face.units_per_em() as f32;
+        let glyph = match face.glyph_index(c) {
+            Some(glyph) => glyph,
+            None => return (0.5, 0.7, 0.0),
+        };
+        let advance = face.glyph_hor_advance(glyph).unwrap_or(0) as f32 / em;
+        match face.glyph_bounding_box(glyph) {
+            Some(bounds) => (advance, bounds.y_max as f32 / em, -bounds.y_min as f32 / em),
+            None => (advance, 0.0, 0.0),
+        }
+    }
+
+    /// Where text of one em starts above its baseline, and the text size
+    /// that draws one em, as the renderer sizes text by its line height.
+    fn line_metrics(&self) -> (f32, f32) {
+        match &self.face {
+            Some(face) => {
+                let em = face.units_per_em() as f32;
+                let line = (face.ascender() - face.descender()) as f32;
+                (face.ascender() as f32 / em, line / em)
+            }
+            None => (0.8, 1.0),
+        }
+    }
+
+    fn glyph(&self, c: char, size: f32) -> Formula {
+        let (advance, ascent, descent) = self.metrics(c);
+        let (line_ascent, line_size) = self.line_metrics();
+        Formula {
+            items: vec![Item::Glyph {
+                character: c,
+                x: 0.0,
+                top: -line_ascent * size,
+                size: line_size * size,
+            }],
+            width: advance * size,
+            ascent: ascent * size,
+            descent: descent * size,
+        }
+    }
+
+    fn text(&self, text: &str, size: f32) -> Formula {
+        let mut formula = Formula::default();
+        for c in text.chars() {
+            let glyph = self.glyph(c, size);
+            formula.append(glyph, formula.width, 0.0);
+        }
+        formula
+    }
+
+    fn list(&self, nodes: &[Node], style: Style) -> (Formula, Class) {
+        let mut formula = Formula::default();
+        let mut previous: Option<Class> = None;
+        for node in nodes {
+            let (part, class) = self.node(node, style);
+            let class = match (class, previous) {
+                // Signs at the start or after another operator are unary.
+                (Some(Class::Bin), None | Some(Class::Bin | Class::Rel | Class::Open))
+                | (Some(Class::Bin), Some(Class::Punct | Class::Op)) => Some(Class::Ord),
+                _ => class,
+            };
+            let mut x = formula.width;
+            if let (Some(left), Some(right)) = (previous, class) {
+                if style.level == 0 {
+                    x += spacing(left, right) * style.size();
+                }
+            }
+            formula.append(part, x, 0.0);
+            if class.is_some() {
+                previous = class;
+            }
+        }
+        (formula, Class::Ord)
+    }
+
+    fn node(&self, node: &Node, style: Style) -> (Formula, Option<Class>) {
+        let size = style.size();
+        match node {
+            Node::Char(c, class) => (self.glyph(*c, size), Some(*class)),
+            Node::Text(text) => (self.text(text, size), Some(Class::Ord)),
+            Node::Space(em) => (
+                Formula {
+                    width: em * size,
+                    ..Formula::default()
+                },
+                None,
+            ),
+            Node::Group(nodes) => {
+                let (formula, class) = self.list(nodes, style);
+                (formula, Some(class))
+            }
+            Node::Operator { text, big, .. } => {
+                if !big {
+                    return (self.text(text, size), Some(Class::Op));
+                }
+                let scale = if style.display { 1.4 } else { 1.0 };
+                let symbol = self.text(text, size * scale);
+                (self.centered(symbol, size), Some(Class::Op))
+            }
+            Node::Scripts { base, sub, sup } => self.scripts(base, sub, sup, style),
+            Node::Fraction(numerator, denominator) => (
+                self.fraction(numerator, denominator, style),
+                Some(Class::Ord),
+            ),
+            Node::Root { body, index } => (self.root(body, index, style), Some(Class::Ord)),
+            Node::Accent(accent, body) => (self.accent(*accent, body, style), Some(Class::Ord)),
+            Node::Delimited(left, body, right) => {
+                (self.delimited(*left, body, *right, style), Some(Class::Ord))
+            }
+        }
+    }
+
+    /// Shifts `formula` so that its middle sits on the axis.
+    fn centered(&self, formula: Formula, size: f32) -> Formula {
+        let shift = (formula.ascent - formula.descent) / 2.0 - AXIS * size;
+        let mut centered = Formula::default();
+        centered.append(formula, 0.0, shift);
+        centered
+    }
+
+    fn scripts(
+        &self,
+        base: &Node,
+        sub: &Option<Box<Node>>,
+        sup: &Option<Box<Node>>,
+        style: Style,
+    ) -> (Formula, Option<Class>) {
+        let size = style.size();
+        let (base_formula, class) = self.node(base, style);
+        let sub = sub.as_ref().map(|sub| self.node(sub, style.script()).0);
+        let sup = sup.as_ref().map(|sup| self.node(sup, style.script()).0);
+
+        let limits = matches!(base, Node::Operator { limits: true, .. });
+        if limits && style.display {
+            let gap = 0.15 * size;
+            let width = [Some(&base_formula), sub.as_ref(), sup.as_ref()]
+                .iter()
+                .flatten()
+                .map(|formula| formula.width)
+                .fold(0.0, f32::max);
+            let (ascent, descent) = (base_formula.ascent, base_formula.descent);
+            let mut formula = Formula::default();
+            formula.append_centered(base_formula, width, 0.0);
+            if let Some(sup) = sup {
+                let shift = -(ascent + gap + sup.descent);
+                formula.append_centered(sup, width, shift);
+            }
+            if let Some(sub) = sub {
+                let shift = descent + gap + sub.ascent;
+                formula.append_centered(sub, width, shift);
+            }
+            return (formula, class);
+        }
+
+        let mut raise = (base_formula.ascent - 0.35 * size).max(0.4 * size);
+        let mut lower = (base_formula.descent + 0.1 * size).max(0.2 * size);
+        if let (Some(sup), Some(sub)) = (&sup, &sub) {
+            let gap = (raise - sup.descent) - (sub.ascent - lower);
+            if gap < 0.1 * size {
+                lower += 0.1 * size - gap;
+            }
+        }
+        if sup.is_none() {
+            raise = 0.0;
+        }
+
+        let x = base_formula.width;
+        let mut formula = base_formula;
+        if let Some(sup) = sup {
+            formula.append(sup, x, -raise);
+        }
+        if let Some(sub) = sub {
+            formula.append(sub, x, lower);
+        }
+        (formula, class)
+    }
+
+    fn fraction(&self, numerator: &Node, denominator: &Node, style: Style) -> Formula {
+        let size = style.size();
+        let numerator = self.node(numerator, style.fraction()).0;
+        let denominator = self.node(denominator, style.fraction()).0;
+        let padding = 0.1 * size;
+        let width = numerator.width.max(denominator.width) + 2.0 * padding;
+        let axis = AXIS * size;
+        let thickness = (RULE * size).max(1.0);
+        let gap = if style.display { 0.15 } else { 0.1 } * size;
+
+        let mut formula = Formula::default();
+        let shift = -(axis + thickness / 2.0 + gap + numerator.descent);
+        formula.append_centered(numerator, width, shift);
+        let shift = -axis + thickness / 2.0 + gap + denominator.ascent;
+        formula.append_centered(denominator, width, shift);
+        formula.line((0.0, -axis), (width, -axis), thickness);
+        formula.width = width;
+        formula
+    }
+
+    fn root(&self, body: &Node, index: &Option<Box<Node>>, style: Style) -> Formula {
+        let size = style.size();
+        let body = self.node(body, style).0;
+        let thickness = (RULE * size).max(1.0);
+        let gap = 0.12 * size;
+        let top = -(body.ascent + gap);
+        let bottom = body.descent;
+        let height = bottom - top;
+
+        let mut formula = Formula::default();
+        let mut x = 0.0;
+        if let Some(index) = index {
+            let index = self.node(index, style.script().script()).0;
+            let shift = bottom - 0.55 * height - index.descent;
+            x = (index.width - 0.2 * size).max(0.0);
+            formula.append(index, 0.0, shift);
+        }
+
+        let points = [
+            (x, bottom - 0.35 * height),
+            (x + 0.1 * size, bottom - 0.42 * height),
+            (x + 0.3 * size, bottom),
+            (x + 0.55 * size, top),
+            (x + 0.65 * size + body.width, top),
+        ];
+        for pair in points.windows(2) {
+            formula.line(pair[0], pair[1], thickness);
+        }
+        let body_x = x + 0.6 * size;
+        formula.append(body, body_x, 0.0);
+        formula.ascent = formula.ascent.max(-top + thickness);
+        formula.width += 0.05 * size;
+        formula
+    }
+
+    fn accent(&self, accent: Accent, body: &Node, style: Style) -> Formula {
+        let size = style.size();
+        let mut formula = self.node(body, style).0;
+        let thickness = (RULE * size).max(1.0);
+        let width = formula.width;
+        let center = width / 2.0;
+        let y = -(formula.ascent + 0.08 * size);
+
+        match accent {
+            Accent::Bar => formula.line((0.0, y), (width, y), thickness),
+            Accent::Hat => {
+                let half = (width / 2.0).min(0.25 * size);
+                let peak = y - 0.15 * size;
+                formula.line((center - half, y), (center, peak), thickness);
+                formula.line((center, peak), (center + half, y), thickness);
+            }
+            Accent::Vector => {
+                let y = y - 0.08 * size;
+                let head = 0.12 * size;
+                formula.line((0.0, y), (width, y), thickness);
+                formula.line((width - head, y - head * 0.7), (width, y), thickness);
+                formula.line((width - head, y + head * 0.7), (width, y), thickness);
+            }
+            Accent::Dot => {
+                let dot = 0.1 * size;
+                formula.line((center - dot / 2.0, y), (center + dot / 2.0, y), dot);
+            }
+            Accent::Tilde => {
+                let tilde = self.glyph('∼', size * 0.8);
+                let x = center - tilde.width / 2.0;
+                let shift = y - tilde.descent;
+                formula.append(tilde, x, shift);
+            }
+        }
+        formula.ascent = formula.ascent.max(-y + 0.2 * size);
+        formula
+    }
+
+    fn delimited(
+        &self,
+        left: Option<char>,
+        body: &[Node],
+        right: Option<char>,
+        style: Style,
+    ) -> Formula {
+        let size = style.size();
+        let body = self.list(body, style).0;
+        let axis = AXIS * size;
+        let needed = 2.0 * (body.ascent - axis).max(body.descent + axis);
+
+        let delimiter = |c: char| {
+            let (_, ascent, descent) = self.metrics(c);
+            let scale = (needed * 1.05 / ((ascent + descent) * size)).max(1.0);
+            self.centered(self.glyph(c, size * scale), size)
+        };
+
+        let mut formula = Formula::default();
+        if let Some(left) = left {
+            formula.append(delimiter(left), 0.0, 0.0);
+        }
+        let x = formula.width;
+        formula.append(body, x, 0.0);
+        if let Some(right) = right {
+            let x = formula.width;
+            formula.append(delimiter(right), x, 0.0);
+        }
+        formula
+    }
+}
+
+impl<Message> canvas::Program<Message> for Formula {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(bounds.size());
+        let color = theme.palette().text;
+
+        for item in self.items.iter() {
+            match item {
+                Item::Glyph {
+                    character,
+                    x,
+                    top,
+                    size,
+                } => frame.fill_text(Text {
+                    content: character.to_string(),
+                    position: Point::new(*x, *top),
+                    color,
+                    size: *size,
+                    font: FONT,
+                    horizontal_alignment: alignment::Horizontal::Left,
+                    vertical_alignment: alignment::Vertical::Top,
+                }),
+                Item::Line { from, to, width } => frame.stroke(
+                    &Path::line(Point::new(from.0, from.1), Point::new(to.0, to.1)),
+                    Stroke::default().with_color(color).with_width(*width),
+                ),
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Node> {
+        Parser::new(source).list(End::Source)
+    }
+
+    fn letter(c: char) -> Node {
+        Node::Char(math_italic(c), Class::Ord)
+    }
+
+    fn digit(c: char) -> Node {
+        Node::Char(c, Class::Ord)
+    }
+
+    fn group(nodes: Vec<Node>) -> Box<Node> {
+        Box::new(Node::Group(nodes))
+    }
+
+    #[test]
+    fn fractions_take_groups_or_single_atoms() {
+        assert_eq!(
+            parse("\\frac{a}{b}"),
+            vec![Node::Fraction(
+                group(vec![letter('a')]),
+                group(vec![letter('b')])
+            )]
+        );
+        assert_eq!(
+            parse("\\frac12"),
+            vec![Node::Fraction(Box::new(digit('1')), Box::new(digit('2')))]
+        );
+    }
+
+    #[test]
+    fn roots_read_an_optional_index() {
+        assert_eq!(
+            parse("\\sqrt[3]{x}"),
+            vec![Node::Root {
+                body: group(vec![letter('x')]),
+                index: Some(group(vec![digit('3')])),
+            }]
+        );
+        assert_eq!(
+            parse("\\sqrt x"),
+            vec![Node::Root {
+                body: Box::new(letter('x')),
+                index: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn primes_join_the_superscript() {
+        assert_eq!(
+            parse("f'^2_i"),
+            vec![Node::Scripts {
+                base: Box::new(letter('f')),
+                sub: Some(Box::new(letter('i'))),
+                sup: Some(group(vec![Node::Char('′', Class::Ord), digit('2')])),
+            }]
+        );
+        assert_eq!(
+            parse("x''"),
+            vec![Node::Scripts {
+                base: Box::new(letter('x')),
+                sub: None,
+                sup: Some(group(vec![
+                    Node::Char('′', Class::Ord),
+                    Node::Char('′', Class::Ord)
+                ])),
+            }]
+        );
+    }
+
+    #[test]
+    fn left_and_right_delimit_their_contents() {
+        assert_eq!(
+            parse("\\left( x \\right]"),
+            vec![Node::Delimited(Some('('), vec![letter('x')], Some(']'))]
+        );
+        assert_eq!(
+            parse("\\left. x \\right\\}"),
+            vec![Node::Delimited(None, vec![letter('x')], Some('}'))]
+        );
+        // A missing `\right` closes at the end of the source.
+        assert_eq!(
+            parse("\\left( x"),
+            vec![Node::Delimited(Some('('), vec![letter('x')], None)]
+        );
+    }
+
+    #[test]
+    fn text_is_kept_as_written() {
+        assert_eq!(
+            parse("\\text{if } x"),
+            vec![Node::Text("if ".to_owned()), letter('x')]
+        );
+        assert_eq!(parse("\\text{a{b}}"), vec![Node::Text("a{b}".to_owned())]);
+    }
+
+    #[test]
+    fn unknown_commands_are_shown_as_written() {
+        assert_eq!(parse("\\foo"), vec![Node::Text("\\foo".to_owned())]);
+        assert_eq!(parse("\\%"), vec![Node::Char('%', Class::Ord)]);
+    }
+
+    #[test]
+    fn unbalanced_braces_stay_in_their_group() {
+        assert_eq!(
+            parse("{x^} + y"),
+            vec![
+                Node::Group(vec![Node::Scripts {
+                    base: Box::new(letter('x')),
+                    sub: None,
+                    sup: Some(group(Vec::new())),
+                }]),
+                Node::Char('+', Class::Bin),
+                letter('y'),
+            ]
+        );
+        assert_eq!(parse("{x"), vec![Node::Group(vec![letter('x')])]);
+        assert_eq!(parse("x} y"), vec![letter('x'), letter('y')]);
+        assert_eq!(
+            parse("\\frac{a}"),
+            vec![Node::Fraction(group(vec![letter('a')]), group(Vec::new()))]
+        );
+    }
+
+    #[test]
+    fn repeated_scripts_share_a_group() {
+        assert_eq!(
+            parse("x^a^b^c"),
+            vec![Node::Scripts {
+                base: Box::new(letter('x')),
+                sub: None,
+                sup: Some(group(vec![letter('a'), letter('b'), letter('c')])),
+            }]
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_shown_as_written() {
+        for source in [
+            format!("{}x", "{".repeat(10_000)),
+            format!("{}x", "\\sqrt".repeat(10_000)),
+            format!("{}x", "x^".repeat(10_000)),
+        ] {
+            let formula = Formula::layout(&source, 20.0, false);
+            assert!(formula.width > 0.0);
+        }
+    }
+
+    #[test]
+    fn layouts_are_cached() {
+        let formula = Formula::layout("\\frac{a}{b}", 20.0, true);
+        assert!(formula.width > 0.0 && formula.height() > 0.0);
+        assert_eq!(
+            Formula::layout("\\frac{a}{b}", 20.0, true).items,
+            formula.items
+        );
+        let inline = Formula::layout("\\frac{a}{b}", 20.0, false);
+        assert!(inline.height() < formula.height());
+    }
+}
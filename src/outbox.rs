@@ -0,0 +1,418 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::client::{self, Deck, Error, RetryPolicy};
+
+const LOCAL_ID_PREFIX: &str = "local-";
+
+/// A deck change made locally that Toshokan has not seen yet.
+///
+/// `base` is the deck as last fetched from the server; replaying compares it
+/// with the server's current version to notice edits made elsewhere.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Mutation {
+    Create { deck: Deck },
+    Update { base: Deck, deck: Deck },
+    Delete { base: Deck },
+}
+
+impl Mutation {
+    /// Id the affected deck currently has in the local deck list.
+    pub fn local_deck_id(&self) -> &str {
+        match self {
+            Mutation::Create { deck } => &deck.id,
+            Mutation::Update { base, .. } | Mutation::Delete { base } => &base.id,
+        }
+    }
+
+    /// Deck content to upload, absent for deletions.
+    pub fn deck(&self) -> Option<&Deck> {
+        match self {
+            Mutation::Create { deck } | Mutation::Update { deck, .. } => Some(deck),
+            Mutation::Delete { .. } => None,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            Mutation::Create { deck } | Mutation::Update { deck, .. } => &deck.title,
+            Mutation::Delete { base } => &base.title,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Conflict {
+    DeletedOnServer { mutation: Mutation },
+    EditedElsewhere { mutation: Mutation, server: Deck },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    KeepMine,
+    KeepTheirs,
+    KeepBoth,
+}
+
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    Applied {
+        mutation: Mutation,
+        created: Option<Deck>,
+    },
+    Conflict(Conflict),
+}
+
+/// Pending mutations, oldest first, replayed in order once online.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Outbox {
+    #[serde(default)]
+    pub mutations: VecDeque<Mutation>,
+    /// The front mutation has been sent and must not be folded into.
+    #[serde(skip)]
+    pub in_flight: bool,
+}
+
+pub fn local_id() -> String {
+    format!(
+        "{}{}-{:08x}",
+        LOCAL_ID_PREFIX,
+        chrono::Utc::now().timestamp_millis(),
+        rand::thread_rng().gen::<u32>()
+    )
+}
+
+impl Outbox {
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+
+    pub fn front(&self) -> Option<&Mutation> {
+        self.mutations.front()
+    }
+
+    pub fn pop_front(&mut self) -> Option<Mutation> {
+        self.mutations.pop_front()
+    }
+
+    pub fn replace_front(&mut self, mutation: Mutation) {
+        if let Some(front) = self.mutations.front_mut() {
+            *front = mutation;
+        }
+    }
+
+    /// Whether a queued mutation still has to change the deck with `id`.
+    pub fn touches(&self, id: &str) -> bool {
+        self.mutations.iter().any(|m| m.local_deck_id() == id)
+    }
+
+    /// Queues `mutation`, folding it into one already queued for the same
+    /// deck so that replaying never sends intermediate states.
+    pub fn push(&mut self, mutation: Mutation) {
+        let first = if self.in_flight { 1 } else { 0 };
+
+        match mutation {
+            Mutation::Create { .. } => self.mutations.push_back(mutation),
+            Mutation::Update { base, deck } => {
+                let queued = self
+                    .mutations
+                    .iter_mut()
+                    .skip(first)
+                    .find(|m| m.local_deck_id() == base.id);
+                match queued {
+                    Some(Mutation::Create { deck: queued_deck })
+                    | Some(Mutation::Update {
+                        deck: queued_deck, ..
+                    }) => *queued_deck = deck,
+                    _ => self.mutations.push_back(Mutation::Update { base, deck }),
+                }
+            }
+            Mutation::Delete { base } => {
+                let queued: Vec<Mutation> = self
+                    .mutations
+                    .iter()
+                    .skip(first)
+                    .filter(|m| m.local_deck_id() == base.id)
+                    .cloned()
+                    .collect();
+                let created_locally = queued.iter().any(|m| matches!(m, Mutation::Create { .. }));
+                let original = queued.into_iter().find_map(|m| match m {
+                    Mutation::Update { base: original, .. } => Some(original),
+                    _ => None,
+                });
+
+                let mut index = 0;
+                self.mutations.retain(|m| {
+                    index += 1;
+                    index <= first || m.local_deck_id() != base.id
+                });
+                if !created_locally {
+                    self.mutations.push_back(Mutation::Delete {
+                        base: original.unwrap_or(base),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Points queued mutations at the deck the server created for `old_id`,
+    /// which is now what later updates and deletions are based on.
+    pub fn rebase(&mut self, old_id: &str, created: &Deck) {
+        for mutation in self.mutations.iter_mut() {
+            match mutation {
+                Mutation::Create { deck } if deck.id == old_id => deck.id = created.id.clone(),
+                Mutation::Update { base, deck } if base.id == old_id => {
+                    *base = created.clone();
+                    deck.id = created.id.clone();
+                }
+                Mutation::Delete { base } if base.id == old_id => *base = created.clone(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Lays the queued changes over a deck list fetched from the server.
+    pub fn apply_to(&self, decks: &mut Vec<Deck>) {
+        for mutation in self.mutations.iter() {
+            match mutation {
+                Mutation::Create { deck } => decks.push(deck.clone()),
+                Mutation::Update { base, deck } => {
+                    if let Some(index) = decks.iter().position(|d| d.id == base.id) {
+                        decks[index] = deck.clone();
+                    }
+                }
+                Mutation::Delete { base } => decks.retain(|d| d.id != base.id),
+            }
+        }
+    }
+}
+
+fn edited_since(base: &Deck, server: &Deck) -> bool {
+    base.title != server.title
+        || base.description != server.description
+        || base.folder != server.folder
+        || base.tags != server.tags
+        || base.is_public != server.is_public
+        || (!base.cards.is_empty() && base.cards != server.cards)
+}
+
+/// Sends one queued mutation. Unless `force` is set, updates and deletions
+/// first check that the server still holds the deck they were based on.
+pub async fn replay(
+    token: String,
+    mutation: Mutation,
+    force: bool,
+    retry: RetryPolicy,
+) -> Result<Outcome, Error> {
+    if let Mutation::Update { base, .. } | Mutation::Delete { base } = &mutation {
+        if !force {
+            match client::get_deck(token.clone(), base.id.clone(), retry).await {
+                Ok(server) if edited_since(base, &server) => {
+                    return Ok(Outcome::Conflict(Conflict::EditedElsewhere {
+                        mutation,
+                        server,
+                    }));
+                }
+                Ok(_) => {}
+                Err(Error::NotFound) => {
+                    if let Mutation::Delete { .. } = mutation {
+                        return Ok(Outcome::Applied {
+                            mutation,
+                            created: None,
+                        });
+                    }
+                    return Ok(Outcome::Conflict(Conflict::DeletedOnServer { mutation }));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    match &mutation {
+        Mutation::Create { deck } => {
+            let created = client::create_deck(token, deck.clone(), retry).await?;
+            Ok(Outcome::Applied {
+                mutation,
+                created: Some(created),
+            })
+        }
+        Mutation::Update { base, deck } => {
+            let created = client::create_deck(token.clone(), deck.clone(), retry).await?;
+            match client::delete_deck(token, base.id.clone(), retry).await {
+                Ok(_) | Err(Error::NotFound) => {}
+                Err(err) => eprintln!("Could not remove replaced deck {}: {}", base.id, err),
+            }
+            Ok(Outcome::Applied {
+                mutation,
+                created: Some(created),
+            })
+        }
+        Mutation::Delete { base } => {
+            match client::delete_deck(token, base.id.clone(), retry).await {
+                Ok(_) | Err(Error::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+            Ok(Outcome::Applied {
+                mutation,
+                created: None,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Answer, Card};
+
+    fn deck(id: &str, title: &str) -> Deck {
+        Deck {
+            cards: Vec::new(),
+            owner_id: "owner".to_owned(),
+            folder: Vec::new(),
+            tags: Vec::new(),
+            card_count: 0,
+            created_at: None,
+            is_public: false,
+            author: String::new(),
+            description: String::new(),
+            title: title.to_owned(),
+            id: id.to_owned(),
+        }
+    }
+
+    fn card(title: &str) -> Card {
+        Card {
+            id: "card".to_owned(),
+            title: title.to_owned(),
+            answers: vec![Answer {
+                text: "answer".to_owned(),
+                is_correct: true,
+            }],
+            explanation: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    fn update(id: &str, from: &str, to: &str) -> Mutation {
+        Mutation::Update {
+            base: deck(id, from),
+            deck: deck(id, to),
+        }
+    }
+
+    #[test]
+    fn update_folds_into_queued_create() {
+        let mut outbox = Outbox::default();
+        outbox.push(Mutation::Create {
+            deck: deck("local-1", "Draft"),
+        });
+        outbox.push(update("local-1", "Draft", "Final"));
+
+        assert_eq!(outbox.mutations.len(), 1);
+        assert!(matches!(
+            outbox.front(),
+            Some(Mutation::Create { deck }) if deck.title == "Final"
+        ));
+    }
+
+    #[test]
+    fn update_folds_into_queued_update_keeping_its_base() {
+        let mut outbox = Outbox::default();
+        outbox.push(update("deck", "Server", "First"));
+        outbox.push(update("deck", "First", "Second"));
+
+        assert_eq!(outbox.mutations.len(), 1);
+        assert!(matches!(
+            outbox.front(),
+            Some(Mutation::Update { base, deck })
+                if base.title == "Server" && deck.title == "Second"
+        ));
+    }
+
+    #[test]
+    fn delete_cancels_queued_create() {
+        let mut outbox = Outbox::default();
+        outbox.push(Mutation::Create {
+            deck: deck("local-1", "Draft"),
+        });
+        outbox.push(update("local-1", "Draft", "Final"));
+        outbox.push(Mutation::Delete {
+            base: deck("local-1", "Final"),
+        });
+
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn delete_replaces_queued_update_with_its_base() {
+        let mut outbox = Outbox::default();
+        outbox.push(update("deck", "Server", "Edited"));
+        outbox.push(Mutation::Delete {
+            base: deck("deck", "Edited"),
+        });
+
+        assert_eq!(outbox.mutations.len(), 1);
+        assert!(matches!(
+            outbox.front(),
+            Some(Mutation::Delete { base }) if base.title == "Server"
+        ));
+    }
+
+    #[test]
+    fn in_flight_mutation_is_never_folded_into() {
+        let mut outbox = Outbox::default();
+        outbox.push(Mutation::Create {
+            deck: deck("local-1", "Draft"),
+        });
+        outbox.in_flight = true;
+        outbox.push(update("local-1", "Draft", "Final"));
+
+        assert_eq!(outbox.mutations.len(), 2);
+        assert!(matches!(
+            outbox.front(),
+            Some(Mutation::Create { deck }) if deck.title == "Draft"
+        ));
+
+        outbox.push(Mutation::Delete {
+            base: deck("local-1", "Final"),
+        });
+        // The sent create still has to be undone on the server.
+        assert_eq!(outbox.mutations.len(), 2);
+        assert!(matches!(outbox.front(), Some(Mutation::Create { .. })));
+        assert!(matches!(
+            outbox.mutations.back(),
+            Some(Mutation::Delete { base }) if base.title == "Draft"
+        ));
+    }
+
+    #[test]
+    fn unchanged_server_deck_is_no_conflict() {
+        let base = deck("deck", "Title");
+        assert!(!edited_since(&base, &base.clone()));
+
+        // Deck lists come without cards, which then say nothing.
+        let mut server = base.clone();
+        server.cards = vec![card("Question")];
+        assert!(!edited_since(&base, &server));
+    }
+
+    #[test]
+    fn server_edits_are_conflicts() {
+        let mut base = deck("deck", "Title");
+        base.cards = vec![card("Question")];
+
+        let mut renamed = base.clone();
+        renamed.title = "Renamed".to_owned();
+        assert!(edited_since(&base, &renamed));
+
+        let mut published = base.clone();
+        published.is_public = true;
+        assert!(edited_since(&base, &published));
+
+        let mut edited_card = base.clone();
+        edited_card.cards = vec![card("Other question")];
+        assert!(edited_since(&base, &edited_card));
+    }
+}
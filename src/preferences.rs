@@ -0,0 +1,383 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+use crate::client::RetryPolicy;
+use crate::fsrs::Fsrs;
+use crate::scheduler::SchedulerKind;
+
+/// Longest feedback delay accepted, in seconds.
+const MAX_ANSWER_DELAY_SECONDS: f32 = 60.0;
+
+/// Per-user settings kept on this machine only.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Preferences {
+    #[serde(default)]
+    pub deck_sort: DeckSort,
+    #[serde(default)]
+    pub deck_grouping: DeckGrouping,
+    /// Names of the deck list sections folded away.
+    #[serde(default)]
+    pub collapsed_groups: HashSet<String>,
+    /// Reviews a day the study calendar counts as a goal met.
+    #[serde(default = "default_daily_goal")]
+    pub daily_goal: usize,
+    /// Study by revealing answers instead of choosing among them.
+    #[serde(default)]
+    pub flashcard_mode: bool,
+    /// Study settings by deck id; decks without any use the defaults.
+    #[serde(default)]
+    pub deck_settings: HashMap<String, DeckSettings>,
+    /// FSRS weights fitted to this user's reviews.
+    #[serde(default)]
+    pub fsrs: Option<Fsrs>,
+    /// How long a rated card's feedback stays before the next card.
+    #[serde(default = "default_answer_delay_ms")]
+    pub answer_delay_ms: u64,
+    /// Waits for Enter or Space instead of moving on by itself.
+    #[serde(default)]
+    pub manual_advance: bool,
+    /// Counts the score up on the results instead of showing it at once.
+    #[serde(default = "default_animate_score")]
+    pub animate_score: bool,
+    #[serde(default)]
+    pub exam: ExamSettings,
+    /// Attempts at a request that keeps failing, the first one included.
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    /// Wait before the first retry, doubled for each one after.
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExamSettings {
+    pub limit: ExamLimit,
+    pub seconds: u64,
+    /// Whether answers can be changed, and earlier cards revisited under an
+    /// overall limit, before the exam is submitted.
+    pub allow_changes: bool,
+}
+
+impl Default for ExamSettings {
+    fn default() -> Self {
+        ExamSettings {
+            limit: ExamLimit::Overall,
+            seconds: 600,
+            allow_changes: true,
+        }
+    }
+}
+
+/// What an exam's time limit applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ExamLimit {
+    Overall,
+    PerCard,
+}
+
+impl ExamLimit {
+    pub const ALL: [ExamLimit; 2] = [ExamLimit::Overall, ExamLimit::PerCard];
+}
+
+impl Display for ExamLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            ExamLimit::Overall => "Seconds for the exam",
+            ExamLimit::PerCard => "Seconds per card",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct DeckSettings {
+    #[serde(default)]
+    pub scheduler: SchedulerKind,
+    /// Cards seen for the first time in a day.
+    #[serde(default = "default_new_cards_per_day")]
+    pub new_cards_per_day: usize,
+    /// Reviews of already learnt cards in a day.
+    #[serde(default = "default_reviews_per_day")]
+    pub reviews_per_day: usize,
+    /// Minutes between the reviews of a card being learnt.
+    #[serde(default = "default_learning_steps")]
+    pub learning_steps: Vec<u32>,
+    /// Lapses after which a card counts as a leech, zero to never.
+    #[serde(default = "default_leech_threshold")]
+    pub leech_threshold: u32,
+    #[serde(default)]
+    pub leech_action: LeechAction,
+}
+
+/// What happens to a card once it becomes a leech.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LeechAction {
+    /// Only lists the card as a leech. Tagging it is left to the user, as
+    /// editing a deck recreates it on Toshokan.
+    #[default]
+    #[serde(alias = "Tag")]
+    Mark,
+    Suspend,
+}
+
+impl LeechAction {
+    pub const ALL: [LeechAction; 2] = [LeechAction::Mark, LeechAction::Suspend];
+}
+
+impl Display for LeechAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            LeechAction::Mark => "List it",
+            LeechAction::Suspend => "Suspend it",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+fn default_new_cards_per_day() -> usize {
+    20
+}
+
+fn default_reviews_per_day() -> usize {
+    200
+}
+
+fn default_learning_steps() -> Vec<u32> {
+    vec![1, 10]
+}
+
+fn default_leech_threshold() -> u32 {
+    8
+}
+
+impl Default for DeckSettings {
+    fn default() -> Self {
+        DeckSettings {
+            scheduler: SchedulerKind::default(),
+            new_cards_per_day: default_new_cards_per_day(),
+            reviews_per_day: default_reviews_per_day(),
+            learning_steps: default_learning_steps(),
+            leech_threshold: default_leech_threshold(),
+            leech_action: LeechAction::default(),
+        }
+    }
+}
+
+impl DeckSettings {
+    pub fn is_leech(&self, lapses: u32) -> bool {
+        self.leech_threshold > 0 && lapses >= self.leech_threshold
+    }
+
+    pub fn learning_steps(&self) -> Vec<Duration> {
+        self.learning_steps
+            .iter()
+            .map(|minutes| Duration::minutes(*minutes as i64))
+            .collect()
+    }
+}
+
+/// Reads learning steps written as `1m 10m 1d` into minutes. Numbers
+/// without a unit are minutes.
+pub fn parse_learning_steps(steps: &str) -> Option<Vec<u32>> {
+    steps
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|step| !step.is_empty())
+        .map(|step| {
+            let (number, minutes) = match step.char_indices().last() {
+                Some((at, 'm')) => (&step[..at], 1),
+                Some((at, 'h')) => (&step[..at], 60),
+                Some((at, 'd')) => (&step[..at], 24 * 60),
+                _ => (step, 1),
+            };
+            match number.parse::<u32>() {
+                Ok(number) if number > 0 => number.checked_mul(minutes),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Reads a feedback delay written in seconds into milliseconds, refusing
+/// negative, endless and overly long delays.
+pub fn parse_answer_delay(seconds: &str) -> Option<u64> {
+    match seconds.trim().parse::<f32>() {
+        Ok(seconds)
+            if seconds.is_finite() && (0.0..=MAX_ANSWER_DELAY_SECONDS).contains(&seconds) =>
+        {
+            Some((seconds * 1000.0).round() as u64)
+        }
+        _ => None,
+    }
+}
+
+pub fn format_learning_steps(steps: &[u32]) -> String {
+    steps
+        .iter()
+        .map(|minutes| match minutes {
+            minutes if minutes % (24 * 60) == 0 => format!("{}d", minutes / (24 * 60)),
+            minutes if minutes % 60 == 0 => format!("{}h", minutes / 60),
+            minutes => format!("{}m", minutes),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn default_daily_goal() -> usize {
+    20
+}
+
+fn default_answer_delay_ms() -> u64 {
+    1000
+}
+
+fn default_retry_attempts() -> u32 {
+    RetryPolicy::default().max_attempts
+}
+
+fn default_retry_delay_ms() -> u64 {
+    RetryPolicy::default().base_delay.as_millis() as u64
+}
+
+fn default_animate_score() -> bool {
+    true
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            deck_sort: DeckSort::default(),
+            deck_grouping: DeckGrouping::default(),
+            collapsed_groups: HashSet::new(),
+            daily_goal: default_daily_goal(),
+            flashcard_mode: false,
+            deck_settings: HashMap::new(),
+            fsrs: None,
+            answer_delay_ms: default_answer_delay_ms(),
+            manual_advance: false,
+            animate_score: default_animate_score(),
+            exam: ExamSettings::default(),
+            retry_attempts: default_retry_attempts(),
+            retry_delay_ms: default_retry_delay_ms(),
+        }
+    }
+}
+
+impl Preferences {
+    pub fn deck_settings(&self, deck_id: &str) -> DeckSettings {
+        self.deck_settings.get(deck_id).cloned().unwrap_or_default()
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry_attempts.max(1),
+            base_delay: std::time::Duration::from_millis(self.retry_delay_ms),
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Moves the settings of a deck created offline to its server id.
+    pub fn rekey_deck(&mut self, old_deck_id: &str, new_deck_id: &str) {
+        if let Some(settings) = self.deck_settings.remove(old_deck_id) {
+            self.deck_settings.insert(new_deck_id.to_owned(), settings);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DeckSort {
+    /// The order Toshokan returns decks in.
+    #[default]
+    Popularity,
+    Title,
+    Created,
+    LastStudied,
+    DueCards,
+    CardCount,
+}
+
+impl DeckSort {
+    pub const ALL: [DeckSort; 6] = [
+        DeckSort::Popularity,
+        DeckSort::Title,
+        DeckSort::Created,
+        DeckSort::LastStudied,
+        DeckSort::DueCards,
+        DeckSort::CardCount,
+    ];
+}
+
+impl Display for DeckSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            DeckSort::Popularity => "Popularity",
+            DeckSort::Title => "Title",
+            DeckSort::Created => "Newest first",
+            DeckSort::LastStudied => "Last studied",
+            DeckSort::DueCards => "Due cards",
+            DeckSort::CardCount => "Card count",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DeckGrouping {
+    #[default]
+    None,
+    Folder,
+    Tag,
+}
+
+impl DeckGrouping {
+    pub const ALL: [DeckGrouping; 3] =
+        [DeckGrouping::None, DeckGrouping::Folder, DeckGrouping::Tag];
+}
+
+impl Display for DeckGrouping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            DeckGrouping::None => "No grouping",
+            DeckGrouping::Folder => "Group by folder",
+            DeckGrouping::Tag => "Group by tag",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn learning_steps_read_units_as_minutes() {
+        assert_eq!(parse_learning_steps("1m 10m 1d"), Some(vec![1, 10, 1440]));
+        assert_eq!(parse_learning_steps("5, 2h"), Some(vec![5, 120]));
+        assert_eq!(parse_learning_steps("  "), Some(Vec::new()));
+    }
+
+    #[test]
+    fn learning_steps_refuse_bad_steps() {
+        for steps in ["0", "1x", "-1m", "m", "1.5h", "4294967295d"] {
+            assert_eq!(parse_learning_steps(steps), None, "{}", steps);
+        }
+    }
+
+    #[test]
+    fn retry_settings_default_to_the_built_in_policy() {
+        let preferences: Preferences = serde_json::from_str("{}").unwrap();
+        let policy = preferences.retry_policy();
+        let default = RetryPolicy::default();
+        assert_eq!(policy.max_attempts, default.max_attempts);
+        assert_eq!(policy.base_delay, default.base_delay);
+
+        let preferences: Preferences =
+            serde_json::from_str(r#"{"retry_attempts": 0, "retry_delay_ms": 100}"#).unwrap();
+        assert_eq!(preferences.retry_policy().max_attempts, 1);
+        assert_eq!(
+            preferences.retry_policy().base_delay,
+            std::time::Duration::from_millis(100)
+        );
+    }
+}
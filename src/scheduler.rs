@@ -0,0 +1,364 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+use crate::fsrs::{self, Fsrs};
+
+const INITIAL_EASE: f32 = 2.5;
+const MINIMUM_EASE: f32 = 1.3;
+/// Extra interval growth for cards rated easy.
+const EASY_BONUS: f32 = 1.3;
+
+/// How well a card was recalled, graded by the learner once the answer shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Rating {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Rating {
+    pub const ALL: [Rating; 4] = [Rating::Again, Rating::Hard, Rating::Good, Rating::Easy];
+
+    /// The rating a multiple-choice answer stands for when none was given.
+    pub fn from_correct(correct: bool) -> Self {
+        if correct {
+            Rating::Good
+        } else {
+            Rating::Again
+        }
+    }
+
+    pub fn is_recalled(&self) -> bool {
+        *self != Rating::Again
+    }
+
+    /// SM-2 answer quality on its 0 to 5 scale.
+    fn quality(&self) -> f32 {
+        match self {
+            Rating::Again => 1.0,
+            Rating::Hard => 3.0,
+            Rating::Good => 4.0,
+            Rating::Easy => 5.0,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rating::Again => "AGAIN",
+            Rating::Hard => "HARD",
+            Rating::Good => "GOOD",
+            Rating::Easy => "EASY",
+        }
+    }
+
+    pub fn parse(rating: &str) -> Option<Rating> {
+        Rating::ALL.into_iter().find(|r| r.as_str() == rating)
+    }
+}
+
+impl Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Rating::Again => "Again",
+            Rating::Hard => "Hard",
+            Rating::Good => "Good",
+            Rating::Easy => "Easy",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Where a card stands in its review schedule. The SM-2 and FSRS fields are
+/// each left alone by the other scheduler.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CardState {
+    pub deck_id: String,
+    pub card_id: String,
+    pub due: DateTime<Utc>,
+    pub interval_days: f32,
+    pub ease: f32,
+    pub repetitions: u32,
+    pub lapses: u32,
+    /// FSRS memory stability in days, zero until FSRS first schedules the card.
+    #[serde(default)]
+    pub stability: f32,
+    #[serde(default)]
+    pub difficulty: f32,
+    /// Position in the learning steps while the card is being learnt, or
+    /// relearnt after a lapse.
+    #[serde(default)]
+    pub learning_step: Option<u32>,
+    /// Suspended cards are left out of every round.
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default)]
+    pub reviewed_at: Option<DateTime<Utc>>,
+    /// Last change, which decides between the copies of different machines.
+    pub updated_at: DateTime<Utc>,
+    /// Whether Toshokan already holds this version of the state.
+    #[serde(default)]
+    pub synced: bool,
+}
+
+impl CardState {
+    pub fn new(deck_id: &str, card_id: &str, now: DateTime<Utc>) -> Self {
+        CardState {
+            deck_id: deck_id.to_owned(),
+            card_id: card_id.to_owned(),
+            due: now,
+            interval_days: 0.0,
+            ease: INITIAL_EASE,
+            repetitions: 0,
+            lapses: 0,
+            stability: 0.0,
+            difficulty: 0.0,
+            learning_step: None,
+            suspended: false,
+            reviewed_at: None,
+            updated_at: now,
+            synced: false,
+        }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.due <= now
+    }
+
+    /// Whether the card was never reviewed, or never left its learning steps.
+    pub fn is_new(&self) -> bool {
+        self.repetitions == 0 && self.lapses == 0
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learning_step.is_some()
+    }
+
+    /// Days since the card was last reviewed.
+    pub fn elapsed_days(&self, now: DateTime<Utc>) -> f32 {
+        fsrs::days_between(self.reviewed_at.unwrap_or(self.updated_at), now)
+    }
+
+    /// Reschedules the card after it was rated. New and lapsed cards go
+    /// through `learning_steps` before the scheduler spaces them out.
+    pub fn review(
+        &mut self,
+        scheduler: &dyn Scheduler,
+        learning_steps: &[Duration],
+        rating: Rating,
+        now: DateTime<Utc>,
+    ) {
+        let step = match self.learning_step {
+            Some(step) => Some(step as usize),
+            None if self.is_new() && !learning_steps.is_empty() => Some(0),
+            None => None,
+        };
+
+        match step {
+            Some(step) => {
+                let next = match rating {
+                    Rating::Again => 0,
+                    Rating::Hard => step,
+                    Rating::Good => step + 1,
+                    Rating::Easy => learning_steps.len(),
+                };
+                if next < learning_steps.len() {
+                    self.learning_step = Some(next as u32);
+                    self.due = now + learning_steps[next];
+                } else {
+                    self.graduate(scheduler, rating, now);
+                }
+            }
+            None => {
+                self.interval_days = scheduler.schedule(self, rating, now);
+                if rating.is_recalled() {
+                    self.repetitions += 1;
+                } else {
+                    self.repetitions = 0;
+                    self.lapses += 1;
+                }
+
+                match learning_steps.first() {
+                    Some(first_step) if !rating.is_recalled() => {
+                        self.learning_step = Some(0);
+                        self.due = now + *first_step;
+                    }
+                    _ => self.due = now + days(self.interval_days),
+                }
+            }
+        }
+
+        self.reviewed_at = Some(now);
+        self.updated_at = now;
+        self.synced = false;
+    }
+
+    pub fn set_suspended(&mut self, suspended: bool, now: DateTime<Utc>) {
+        self.suspended = suspended;
+        self.updated_at = now;
+        self.synced = false;
+    }
+
+    /// Ends the learning steps. Relearnt cards keep the interval the
+    /// scheduler gave them when they lapsed.
+    fn graduate(&mut self, scheduler: &dyn Scheduler, rating: Rating, now: DateTime<Utc>) {
+        if self.is_new() {
+            self.interval_days = scheduler.schedule(self, rating, now);
+            self.repetitions += 1;
+        }
+        self.learning_step = None;
+        self.due = now + days(self.interval_days);
+    }
+}
+
+fn days(days: f32) -> Duration {
+    Duration::seconds((days * 86_400.0) as i64)
+}
+
+/// A spaced repetition algorithm deciding when a card is seen next.
+pub trait Scheduler {
+    /// Updates the algorithm's own fields of `state` for a review rated
+    /// `rating` and returns the days until the next one. Repetitions and
+    /// lapses still hold their values from before the review.
+    fn schedule(&self, state: &mut CardState, rating: Rating, now: DateTime<Utc>) -> f32;
+}
+
+/// The SuperMemo 2 algorithm, growing intervals by a per-card ease factor.
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+    fn schedule(&self, state: &mut CardState, rating: Rating, _now: DateTime<Utc>) -> f32 {
+        let quality = rating.quality();
+        state.ease += 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02);
+        state.ease = state.ease.max(MINIMUM_EASE);
+
+        if !rating.is_recalled() {
+            return 1.0;
+        }
+        let interval = match state.repetitions {
+            0 => 1.0,
+            1 => 6.0,
+            _ => (state.interval_days * state.ease).round(),
+        };
+        if rating == Rating::Easy {
+            (interval * EASY_BONUS).round()
+        } else {
+            interval
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SchedulerKind {
+    #[default]
+    Sm2,
+    Fsrs,
+}
+
+impl SchedulerKind {
+    pub const ALL: [SchedulerKind; 2] = [SchedulerKind::Sm2, SchedulerKind::Fsrs];
+
+    /// The scheduler of this kind, FSRS using the given weights.
+    pub fn scheduler(&self, fsrs: &Fsrs) -> Box<dyn Scheduler> {
+        match self {
+            SchedulerKind::Sm2 => Box::new(Sm2),
+            SchedulerKind::Fsrs => Box::new(fsrs.clone()),
+        }
+    }
+}
+
+impl Display for SchedulerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            SchedulerKind::Sm2 => "SM-2",
+            SchedulerKind::Fsrs => "FSRS",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps() -> Vec<Duration> {
+        vec![Duration::minutes(1), Duration::minutes(10)]
+    }
+
+    fn new_card(now: DateTime<Utc>) -> CardState {
+        CardState::new("deck", "card", now)
+    }
+
+    #[test]
+    fn new_cards_walk_the_learning_steps() {
+        let now = Utc::now();
+        let mut state = new_card(now);
+
+        state.review(&Sm2, &steps(), Rating::Good, now);
+        assert_eq!(state.learning_step, Some(1));
+        assert_eq!(state.due, now + Duration::minutes(10));
+
+        state.review(&Sm2, &steps(), Rating::Hard, now);
+        assert_eq!(state.learning_step, Some(1));
+
+        state.review(&Sm2, &steps(), Rating::Again, now);
+        assert_eq!(state.learning_step, Some(0));
+        assert_eq!(state.due, now + Duration::minutes(1));
+        assert!(state.is_new(), "misses in the steps are not lapses");
+    }
+
+    #[test]
+    fn cards_graduate_past_the_last_step() {
+        let now = Utc::now();
+        let mut state = new_card(now);
+        state.review(&Sm2, &steps(), Rating::Good, now);
+        state.review(&Sm2, &steps(), Rating::Good, now);
+
+        assert_eq!(state.learning_step, None);
+        assert_eq!(state.repetitions, 1);
+        assert_eq!(state.interval_days, 1.0);
+        assert_eq!(state.due, now + Duration::days(1));
+
+        let mut easy = new_card(now);
+        easy.review(&Sm2, &steps(), Rating::Easy, now);
+        assert_eq!(easy.learning_step, None);
+        assert_eq!(easy.repetitions, 1);
+    }
+
+    #[test]
+    fn lapsed_cards_relearn_and_keep_their_new_interval() {
+        let now = Utc::now();
+        let mut state = CardState {
+            repetitions: 3,
+            interval_days: 15.0,
+            ..new_card(now)
+        };
+
+        state.review(&Sm2, &steps(), Rating::Again, now);
+        assert_eq!(state.lapses, 1);
+        assert_eq!(state.repetitions, 0);
+        assert_eq!(state.learning_step, Some(0));
+        assert_eq!(state.due, now + Duration::minutes(1));
+        assert_eq!(state.interval_days, 1.0);
+
+        state.review(&Sm2, &steps(), Rating::Good, now);
+        state.review(&Sm2, &steps(), Rating::Good, now);
+        assert_eq!(state.learning_step, None);
+        assert_eq!(state.repetitions, 0);
+        assert_eq!(state.due, now + Duration::days(1));
+    }
+
+    #[test]
+    fn without_steps_the_scheduler_decides_at_once() {
+        let now = Utc::now();
+        let mut state = new_card(now);
+        state.review(&Sm2, &[], Rating::Good, now);
+        assert_eq!(state.learning_step, None);
+        assert_eq!(state.due, now + Duration::days(1));
+
+        state.review(&Sm2, &[], Rating::Again, now);
+        assert_eq!(state.learning_step, None);
+        assert_eq!(state.lapses, 1);
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::fsrs::{self, Fsrs, Memory};
+use crate::history::Review;
+use crate::scheduler::{CardState, Rating, SchedulerKind};
+
+/// How far ahead simulations study.
+pub const SIMULATION_DAYS: i64 = 90;
+
+/// Predicted outcome of studying with one scheduler.
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    pub scheduler: SchedulerKind,
+    /// Reviews a day over the simulated period.
+    pub reviews_per_day: f32,
+    /// Average recall probability of all cards at the end of the period.
+    pub retention: f32,
+}
+
+struct SimulatedCard {
+    state: CardState,
+    memory: Option<Memory>,
+    last_review: DateTime<Utc>,
+}
+
+impl SimulatedCard {
+    fn review(&mut self, kind: SchedulerKind, model: &Fsrs, rating: Rating, at: DateTime<Utc>) {
+        let elapsed = fsrs::days_between(self.last_review, at);
+        self.memory = Some(model.next_memory(self.memory, elapsed, rating));
+        self.state
+            .review(kind.scheduler(model).as_ref(), &[], rating, at);
+        self.last_review = at;
+    }
+}
+
+/// Replays the review history under the scheduler of `kind`, then keeps
+/// studying every due card for [`SIMULATION_DAYS`]. Whether a card is
+/// recalled is drawn from the `model` memory predictions, with a fixed seed
+/// so that schedulers are compared on the same luck.
+pub fn simulate(
+    reviews: &[Review],
+    kind: SchedulerKind,
+    model: &Fsrs,
+    now: DateTime<Utc>,
+) -> Simulation {
+    let mut cards: Vec<SimulatedCard> = fsrs::review_sequences(reviews)
+        .into_iter()
+        .map(|sequence| {
+            let first = sequence[0];
+            let mut card = SimulatedCard {
+                state: CardState::new(&first.deck_id, &first.card_id, first.reviewed_at),
+                memory: None,
+                last_review: first.reviewed_at,
+            };
+            for review in sequence {
+                card.review(kind, model, fsrs::review_rating(review), review.reviewed_at);
+            }
+            card
+        })
+        .collect();
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut reviews = 0;
+    for day in 0..SIMULATION_DAYS {
+        let today = now + Duration::days(day);
+        for card in cards.iter_mut().filter(|card| card.state.is_due(today)) {
+            let stability = card.memory.map_or(0.0, |memory| memory.stability);
+            let recall =
+                model.retrievability(fsrs::days_between(card.last_review, today), stability);
+            let rating = Rating::from_correct(rng.gen::<f32>() < recall);
+            card.review(kind, model, rating, today);
+            reviews += 1;
+        }
+    }
+
+    let end = now + Duration::days(SIMULATION_DAYS);
+    let retention = cards
+        .iter()
+        .map(|card| {
+            let stability = card.memory.map_or(0.0, |memory| memory.stability);
+            model.retrievability(fsrs::days_between(card.last_review, end), stability)
+        })
+        .sum::<f32>()
+        / cards.len().max(1) as f32;
+
+    Simulation {
+        scheduler: kind,
+        reviews_per_day: reviews as f32 / SIMULATION_DAYS as f32,
+        retention,
+    }
+}
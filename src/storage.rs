@@ -0,0 +1,164 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const HISTORY_FILE: &str = "history.json";
+pub const DECK_CACHE_FILE: &str = "decks.json";
+pub const OUTBOX_FILE: &str = "outbox.json";
+pub const PREFERENCES_FILE: &str = "preferences.json";
+pub const ROUND_FILE: &str = "round.json";
+
+/// A saved file that could not be read back. It is moved aside to
+/// `kept_at` so that saving fresh data does not destroy it.
+#[derive(Clone, Debug)]
+pub struct CorruptFile {
+    pub file: String,
+    pub kept_at: Option<PathBuf>,
+    pub error: String,
+}
+
+impl Display for CorruptFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kept_at {
+            Some(kept_at) => write!(
+                f,
+                "Could not read {} ({}), it was kept as {}",
+                self.file,
+                self.error,
+                kept_at.display()
+            ),
+            None => write!(f, "Could not read {} ({})", self.file, self.error),
+        }
+    }
+}
+
+fn user_dir(username: &str) -> Option<PathBuf> {
+    if username.is_empty() {
+        return None;
+    }
+
+    dirs::data_dir().map(|dir| dir.join("shisho").join(username))
+}
+
+/// Reads a saved file, which is `None` when it was never written.
+pub fn load<T: DeserializeOwned>(username: &str, file: &str) -> Result<Option<T>, CorruptFile> {
+    match user_dir(username) {
+        Some(dir) => load_from(&dir, file),
+        None => Ok(None),
+    }
+}
+
+fn load_from<T: DeserializeOwned>(dir: &Path, file: &str) -> Result<Option<T>, CorruptFile> {
+    let path = dir.join(file);
+    let json_str = match std::fs::read_to_string(&path) {
+        Ok(json_str) => json_str,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(keep_aside(dir, file, err.to_string())),
+    };
+
+    serde_json::from_str::<T>(&json_str)
+        .map(Some)
+        .map_err(|err| keep_aside(dir, file, err.to_string()))
+}
+
+/// Moves an unreadable file out of the way of the next save.
+fn keep_aside(dir: &Path, file: &str, error: String) -> CorruptFile {
+    let kept_at = dir.join(format!(
+        "{}.{}.corrupt",
+        file,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    let kept_at = std::fs::rename(dir.join(file), &kept_at)
+        .ok()
+        .map(|_| kept_at);
+    CorruptFile {
+        file: file.to_owned(),
+        kept_at,
+        error,
+    }
+}
+
+pub fn save<T: Serialize>(username: &str, file: &str, value: &T) {
+    let dir = match user_dir(username) {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    if let Err(err) = save_to(&dir, file, value) {
+        eprintln!("Could not save {}: {}", file, err);
+    }
+}
+
+/// Writes the whole file next to the old one before swapping it in, so
+/// that a crash or a full disk never leaves a half written file behind.
+fn save_to<T: Serialize>(dir: &Path, file: &str, value: &T) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let json_str = serde_json::to_string(value)?;
+
+    let temporary = dir.join(format!("{}.tmp", file));
+    let mut temporary_file = std::fs::File::create(&temporary)?;
+    temporary_file.write_all(json_str.as_bytes())?;
+    temporary_file.sync_all()?;
+    drop(temporary_file);
+
+    std::fs::rename(&temporary, dir.join(file))
+}
+
+pub fn remove(username: &str, file: &str) {
+    let dir = match user_dir(username) {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    match std::fs::remove_file(dir.join(file)) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            eprintln!("Could not remove {}: {}", file, err);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shisho-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn saved_values_load_back() {
+        let dir = test_dir("round-trip");
+        save_to(&dir, "values.json", &vec![1, 2, 3]).unwrap();
+
+        assert_eq!(load_from(&dir, "values.json").unwrap(), Some(vec![1, 2, 3]));
+        assert!(!dir.join("values.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_files_load_as_none() {
+        let dir = test_dir("missing");
+        assert_eq!(load_from::<Vec<u32>>(&dir, "values.json").unwrap(), None);
+    }
+
+    #[test]
+    fn unreadable_files_are_kept_aside() {
+        let dir = test_dir("corrupt");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("values.json"), "[1, 2").unwrap();
+
+        let err = load_from::<Vec<u32>>(&dir, "values.json").unwrap_err();
+        let kept_at = err.kept_at.expect("the file is moved aside");
+        assert_eq!(std::fs::read_to_string(kept_at).unwrap(), "[1, 2");
+        assert!(!dir.join("values.json").exists());
+
+        // Saving afresh leaves the kept copy alone.
+        save_to(&dir, "values.json", &vec![4]).unwrap();
+        assert_eq!(load_from(&dir, "values.json").unwrap(), Some(vec![4]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -18,6 +18,24 @@ pub fn card_style() -> iced::theme::Container {
     iced::theme::Container::Custom(Box::new(CardStyle {}))
 }
 
+pub struct CodeStyle {}
+
+impl iced::widget::container::StyleSheet for CodeStyle {
+    type Style = iced::theme::Theme;
+    fn appearance(&self, _style: &Self::Style) -> iced::widget::container::Appearance {
+        let mut appearance = iced::widget::container::Appearance::default();
+        appearance.border_radius = 4.0;
+        appearance.background = Some(iced::Background::Color(iced::Color::from_rgba8(
+            0, 0, 0, 0.08,
+        )));
+        appearance
+    }
+}
+
+pub fn code_style() -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(CodeStyle {}))
+}
+
 pub struct InvisibleCardButton {}
 
 impl iced::widget::button::StyleSheet for InvisibleCardButton {
@@ -144,3 +162,7 @@ pub fn score_text_color(score: f32) -> iced::Color {
         iced::Color::from_rgba8(255, 255, 255, 1.0)
     }
 }
+
+pub fn chart_color() -> iced::Color {
+    iced::Color::from_rgba8(77, 166, 255, 1.0)
+}
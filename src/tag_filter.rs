@@ -0,0 +1,227 @@
+use std::fmt::{self, Display};
+
+/// A boolean expression over card tags such as `verbs AND NOT irregular`.
+///
+/// `NOT` binds tighter than `AND`, which binds tighter than `OR`; parentheses
+/// group. Keywords are case-insensitive and so are tags.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TagFilter {
+    Tag(String),
+    Not(Box<TagFilter>),
+    And(Box<TagFilter>, Box<TagFilter>),
+    Or(Box<TagFilter>, Box<TagFilter>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError(String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Tag(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl TagFilter {
+    pub fn parse(input: &str) -> Result<TagFilter, ParseError> {
+        let tokens = tokenize(input);
+        if tokens.is_empty() {
+            return Err(ParseError("the expression is empty".to_owned()));
+        }
+
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let filter = parser.any()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => Err(ParseError(format!("unexpected {}", describe(token)))),
+        }
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            TagFilter::Tag(tag) => tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            TagFilter::Not(inner) => !inner.matches(tags),
+            TagFilter::And(left, right) => left.matches(tags) && right.matches(tags),
+            TagFilter::Or(left, right) => left.matches(tags) || right.matches(tags),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let spaced = input.replace('(', " ( ").replace(')', " ) ");
+
+    spaced
+        .split_whitespace()
+        .map(|word| match word.to_uppercase().as_str() {
+            "AND" => Token::And,
+            "OR" => Token::Or,
+            "NOT" => Token::Not,
+            "(" => Token::Open,
+            ")" => Token::Close,
+            _ => Token::Tag(word.to_owned()),
+        })
+        .collect()
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Tag(tag) => format!("tag \"{}\", expected AND or OR", tag),
+        Token::And => "AND".to_owned(),
+        Token::Or => "OR".to_owned(),
+        Token::Not => "NOT".to_owned(),
+        Token::Open => "\"(\"".to_owned(),
+        Token::Close => "\")\"".to_owned(),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn any(&mut self) -> Result<TagFilter, ParseError> {
+        let mut filter = self.all()?;
+        while let Some(Token::Or) = self.peek() {
+            self.advance();
+            filter = TagFilter::Or(Box::new(filter), Box::new(self.all()?));
+        }
+        Ok(filter)
+    }
+
+    fn all(&mut self) -> Result<TagFilter, ParseError> {
+        let mut filter = self.term()?;
+        while let Some(Token::And) = self.peek() {
+            self.advance();
+            filter = TagFilter::And(Box::new(filter), Box::new(self.term()?));
+        }
+        Ok(filter)
+    }
+
+    fn term(&mut self) -> Result<TagFilter, ParseError> {
+        match self.advance() {
+            Some(Token::Not) => Ok(TagFilter::Not(Box::new(self.term()?))),
+            Some(Token::Tag(tag)) => Ok(TagFilter::Tag(tag.clone())),
+            Some(Token::Open) => {
+                let filter = self.any()?;
+                match self.advance() {
+                    Some(Token::Close) => Ok(filter),
+                    _ => Err(ParseError("missing \")\"".to_owned())),
+                }
+            }
+            Some(token) => Err(ParseError(format!(
+                "unexpected {}, expected a tag",
+                describe(token)
+            ))),
+            None => Err(ParseError("expected a tag at the end".to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> Box<TagFilter> {
+        Box::new(TagFilter::Tag(name.to_owned()))
+    }
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            TagFilter::parse("a OR b AND c"),
+            Ok(TagFilter::Or(
+                tag("a"),
+                Box::new(TagFilter::And(tag("b"), tag("c")))
+            ))
+        );
+        assert_eq!(
+            TagFilter::parse("a AND b OR c"),
+            Ok(TagFilter::Or(
+                Box::new(TagFilter::And(tag("a"), tag("b"))),
+                tag("c")
+            ))
+        );
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert_eq!(
+            TagFilter::parse("NOT a AND b"),
+            Ok(TagFilter::And(Box::new(TagFilter::Not(tag("a"))), tag("b")))
+        );
+        assert_eq!(
+            TagFilter::parse("not not a"),
+            Ok(TagFilter::Not(Box::new(TagFilter::Not(tag("a")))))
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(
+            TagFilter::parse("(a OR b) AND c"),
+            Ok(TagFilter::And(
+                Box::new(TagFilter::Or(tag("a"), tag("b"))),
+                tag("c")
+            ))
+        );
+        assert_eq!(
+            TagFilter::parse("NOT(a or b)"),
+            Ok(TagFilter::Not(Box::new(TagFilter::Or(tag("a"), tag("b")))))
+        );
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for input in [
+            "", "   ", "a AND", "AND a", "a b", "(a OR b", "a OR b)", "()", "NOT",
+        ] {
+            assert!(TagFilter::parse(input).is_err(), "{:?} parsed", input);
+        }
+    }
+
+    #[test]
+    fn matches_tags_ignoring_case() {
+        let filter = TagFilter::parse("Verbs and not IRREGULAR").unwrap();
+        assert!(filter.matches(&tags(&["verbs"])));
+        assert!(filter.matches(&tags(&["VERBS", "regular"])));
+        assert!(!filter.matches(&tags(&["verbs", "irregular"])));
+        assert!(!filter.matches(&tags(&[])));
+    }
+
+    #[test]
+    fn unknown_tags_match_no_card() {
+        let filter = TagFilter::parse("missing").unwrap();
+        assert!(!filter.matches(&tags(&["verbs", "nouns"])));
+
+        let filter = TagFilter::parse("NOT missing").unwrap();
+        assert!(filter.matches(&tags(&["verbs"])));
+        assert!(filter.matches(&tags(&[])));
+    }
+}